@@ -1,10 +1,23 @@
-use std::path::PathBuf;
+use std::fmt::Write;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
+use anyhow::Context;
+use boiler_core::actions::{ActionName, CustomAction};
+use boiler_core::context::{ContextStage, RepoConfig, ReposConfig, REPO_CONFIG_FILENAME};
 use boiler_core::data::Repo;
-use boiler_core::run_in_repo;
-use clap::{Parser, Subcommand};
+use boiler_core::detectors::DetectorName;
+use boiler_core::file_sink::{FileChange, MemoryFileSink};
+use boiler_core::lock::LOCK_FILENAME;
+use boiler_core::plugins::Plugins;
+use boiler_core::report::{ActionStatus, FileStatus, RunReport};
+use boiler_core::scaffold::scaffold_repo_config;
+use boiler_core::{git, Boiler, RunOptions};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use clap_verbosity_flag::InfoLevel;
-use color_print::cprintln;
+use color_print::{cprintln, cwriteln};
 use shadow_rs::shadow;
 
 shadow!(build);
@@ -16,6 +29,16 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Command>,
 
+    /// Additional config file with per-repository settings; takes priority
+    /// over the user-level config in ~/.config/boiler/config.yml
+    #[clap(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Directory searched for boiler-detector-* and boiler-action-* plugins
+    /// before ~/.config/boiler/plugins and PATH; can be passed multiple times
+    #[clap(long, global = true)]
+    plugins_dir: Vec<PathBuf>,
+
     #[command(flatten)]
     verbose: clap_verbosity_flag::Verbosity<InfoLevel>,
 }
@@ -26,27 +49,211 @@ impl Cli {
     }
 }
 
+/// The actions and detectors to run, taking priority over the configs.
+///
+/// Only the built-in and plugin actions can be selected, as the custom actions
+/// differ between the repositories.
+#[derive(Args, Debug, Clone, Default)]
+struct Selection {
+    /// Only run the given action, ignoring the actions_included setting; can
+    /// be passed multiple times
+    #[clap(long, value_name = "ACTION")]
+    only: Vec<String>,
+
+    /// Do not run the given action, in addition to the actions_excluded
+    /// setting; can be passed multiple times
+    #[clap(long, value_name = "ACTION")]
+    skip: Vec<String>,
+
+    /// Do not run the given detector, in addition to the detectors_excluded
+    /// setting; can be passed multiple times
+    #[clap(long, value_name = "DETECTOR")]
+    skip_detector: Vec<String>,
+}
+
+impl Selection {
+    fn config_override(&self) -> RepoConfig {
+        let mut config_override = RepoConfig::default()
+            .with_actions_excluded(self.skip.iter().cloned().map(ActionName::new))
+            .with_detectors_excluded(self.skip_detector.iter().cloned().map(DetectorName::new));
+        if !self.only.is_empty() {
+            config_override = config_override
+                .with_actions_included(self.only.iter().cloned().map(ActionName::new));
+        }
+
+        config_override
+    }
+}
+
+impl Command {
+    /// Returns the actions and detectors selected in the arguments, if the
+    /// command runs them.
+    fn selection(&self) -> Option<&Selection> {
+        match self {
+            Self::Update { selection, .. } | Self::Check { selection, .. } => Some(selection),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Subcommand, Debug, Clone)]
 enum Command {
     /// List all detectors with a short description
     ListDetectors,
-    /// List all actions with a short description
-    ListActions,
+    /// List all actions with a short description, including the custom ones
+    /// declared in the config
+    ListActions {
+        /// The repository whose config file is read for custom actions;
+        /// defaults to the current working directory
+        #[clap(long, short)]
+        repo: Option<PathBuf>,
+    },
     /// Run all actions in a repository
     Update {
+        /// The path to the repository; defaults to the current working
+        /// directory. Can be given multiple times to update several
+        /// repositories
+        #[clap(long, short)]
+        repo: Vec<PathBuf>,
+        /// Update all the git repositories found directly in this directory
+        #[clap(long)]
+        scan: Option<PathBuf>,
+        /// The number of repositories to update concurrently; defaults to the
+        /// number of CPUs
+        #[clap(long, short)]
+        jobs: Option<usize>,
+        /// Do not modify any files; print the diff of the changes that would
+        /// be made instead
+        #[clap(long)]
+        dry_run: bool,
+        /// Directory with templates overriding the built-in ones
+        #[clap(long)]
+        templates_dir: Option<PathBuf>,
+        /// Remove the files generated by the previous runs that are no longer
+        /// generated, unless they have been modified since
+        #[clap(long)]
+        prune: bool,
+        /// Commit the changed files, with a message listing the actions that
+        /// changed them
+        #[clap(long, conflicts_with = "dry_run")]
+        commit: bool,
+        /// Make the commit on a new branch with this name, created from the
+        /// current HEAD
+        #[clap(long, requires = "commit")]
+        branch: Option<String>,
+        /// Commit even if the working tree has uncommitted changes; the
+        /// uncommitted changes to the files touched by boiler are committed
+        /// along with the generated content
+        #[clap(long, requires = "commit")]
+        allow_dirty: bool,
+        /// Print the report of what each action did as JSON instead of the
+        /// human-readable output
+        #[clap(long)]
+        json: bool,
+        #[command(flatten)]
+        selection: Selection,
+    },
+    /// Check if the files generated by the actions are up to date; exits with
+    /// a non-zero status code if any of them differ from the ones on disk
+    Check {
         /// The path to the repository; defaults to the current working
         /// directory
         #[clap(long, short)]
         repo: Option<PathBuf>,
+        /// Directory with templates overriding the built-in ones
+        #[clap(long)]
+        templates_dir: Option<PathBuf>,
+        #[command(flatten)]
+        selection: Selection,
     },
+    /// Print the context of a repository, as seen by the templates
+    Context {
+        /// The path to the repository; defaults to the current working
+        /// directory
+        #[clap(long, short)]
+        repo: Option<PathBuf>,
+        /// The output format
+        #[clap(long, short, value_enum, default_value_t)]
+        format: OutputFormat,
+        /// The stage of building the context to print
+        #[clap(long, short, value_enum, default_value_t)]
+        stage: Stage,
+    },
+    /// Create a commented .boiler.yml in the repository, listing the detected
+    /// values, the defaults and the actions that can be excluded
+    Init {
+        /// The path to the repository; defaults to the current working
+        /// directory
+        #[clap(long, short)]
+        repo: Option<PathBuf>,
+        /// Overwrite the existing .boiler.yml
+        #[clap(long)]
+        force: bool,
+    },
+    /// Explain where the value of a context key comes from
+    Explain {
+        /// The context key; nested keys are separated with dots
+        key: String,
+        /// The path to the repository; defaults to the current working
+        /// directory
+        #[clap(long, short)]
+        repo: Option<PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+enum OutputFormat {
+    #[default]
+    Yaml,
+    Json,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+enum Stage {
+    /// Only the values found by the detectors
+    Detected,
+    /// The detected values on top of the defaults
+    Defaults,
+    /// The context with all the config overrides applied
+    #[default]
+    Final,
+}
+
+impl From<Stage> for ContextStage {
+    fn from(stage: Stage) -> Self {
+        match stage {
+            Stage::Detected => Self::Detected,
+            Stage::Defaults => Self::Defaults,
+            Stage::Final => Self::Final,
+        }
+    }
 }
 
 impl Default for Command {
     fn default() -> Self {
-        Command::Update { repo: None }
+        Command::Update {
+            repo: Vec::new(),
+            scan: None,
+            jobs: None,
+            dry_run: false,
+            templates_dir: None,
+            prune: false,
+            commit: false,
+            branch: None,
+            allow_dirty: false,
+            json: false,
+            selection: Selection::default(),
+        }
     }
 }
 
+/// How to commit the changes made by `update`.
+#[derive(Debug, Clone, Default)]
+struct CommitOptions {
+    branch: Option<String>,
+    allow_dirty: bool,
+}
+
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
@@ -56,26 +263,574 @@ fn main() -> anyhow::Result<()> {
 
     match &cli.command() {
         Command::ListDetectors => {
-            list_detectors();
+            let boiler = create_boiler(&cli)?;
+            list_detectors(&boiler);
+        }
+        Command::ListActions { repo } => {
+            let boiler = create_boiler(&cli)?;
+            let repo = Repo::new(repo.clone().unwrap_or(PathBuf::from(".")));
+            list_actions(&repo, &boiler)?;
+        }
+        Command::Update {
+            repo,
+            scan,
+            jobs,
+            dry_run,
+            templates_dir,
+            prune,
+            commit,
+            branch,
+            allow_dirty,
+            json,
+            selection: _,
+        } => {
+            let boiler = create_boiler(&cli)?;
+            let commit_options = commit.then(|| CommitOptions {
+                branch: branch.clone(),
+                allow_dirty: *allow_dirty,
+            });
+            let mut repo_paths = repo.clone();
+            if let Some(scan) = scan {
+                repo_paths.extend(find_repos(scan)?);
+            }
+            let options = RunOptions {
+                templates_dir: templates_dir.clone(),
+                prune: *prune,
+                ..RunOptions::default()
+            };
+            if repo_paths.len() > 1 || scan.is_some() {
+                let jobs = jobs.unwrap_or_else(|| {
+                    std::thread::available_parallelism().map_or(1, NonZeroUsize::get)
+                });
+                update_batch(
+                    &repo_paths,
+                    &boiler,
+                    &options,
+                    *dry_run,
+                    commit_options.as_ref(),
+                    jobs,
+                    *json,
+                )?;
+            } else {
+                let repo_path = repo_paths.pop().unwrap_or(PathBuf::from("."));
+                let mut out = String::new();
+                let result = update(
+                    Repo::new(repo_path),
+                    &boiler,
+                    options,
+                    *dry_run,
+                    commit_options.as_ref(),
+                    &mut out,
+                );
+                if *json {
+                    let report = match &result {
+                        Ok(report) => Some(report),
+                        Err(error) => error
+                            .downcast_ref::<RunFailed>()
+                            .map(|failed| &failed.report),
+                    };
+                    if let Some(report) = report {
+                        println!("{}", serde_json::to_string_pretty(report)?);
+                    }
+                    result?;
+                } else {
+                    print!("{out}");
+                    result?;
+                }
+            }
+        }
+        Command::Check {
+            repo,
+            templates_dir,
+            selection: _,
+        } => {
+            let boiler = create_boiler(&cli)?;
+            let options = RunOptions {
+                templates_dir: templates_dir.clone(),
+                keep_local_changes: false,
+                ..RunOptions::default()
+            };
+            check(
+                Repo::new(repo.clone().unwrap_or(PathBuf::from("."))),
+                &boiler,
+                options,
+            )?;
         }
-        Command::ListActions => {
-            list_actions();
+        Command::Context {
+            repo,
+            format,
+            stage,
+        } => {
+            let boiler = create_boiler(&cli)?;
+            let repo = Repo::new(repo.clone().unwrap_or(PathBuf::from(".")));
+            let context = boiler.build_context(&repo, (*stage).into())?;
+            match format {
+                OutputFormat::Yaml => print!("{}", context.as_yaml()),
+                OutputFormat::Json => println!("{}", context.as_json()),
+            }
         }
-        Command::Update { repo } => {
-            run_in_repo(Repo::new(repo.clone().unwrap_or(PathBuf::from("."))))?;
+        Command::Init { repo, force } => {
+            let boiler = create_boiler(&cli)?;
+            let repo = Repo::new(repo.clone().unwrap_or(PathBuf::from(".")));
+            init(&repo, &boiler, *force)?;
+        }
+        Command::Explain { key, repo } => {
+            let boiler = create_boiler(&cli)?;
+            let repo = Repo::new(repo.clone().unwrap_or(PathBuf::from(".")));
+            explain(&repo, &boiler, key)?;
         }
     }
 
     Ok(())
 }
 
+/// Creates the engine with the built-in config, followed by the user-level
+/// config and the config file passed in the arguments, and the plugins found
+/// in the plugin directories.
+///
+/// The actions and detectors selected in the arguments take priority over all
+/// the configs.
+fn create_boiler(cli: &Cli) -> anyhow::Result<Boiler> {
+    let command = cli.command();
+    let mut builder = Boiler::builder()
+        .repos_config(ReposConfig::load(cli.config.as_deref())?)
+        .plugins(Plugins::discover(&Plugins::search_dirs(&cli.plugins_dir)));
+    if let Some(selection) = command.selection() {
+        builder = builder.config_override("command line", selection.config_override());
+    }
+    builder.build()
+}
+
 fn version_string() -> String {
     format!("{} (commit {})", build::PKG_VERSION, build::SHORT_COMMIT)
 }
 
-fn list_detectors() {
-    let detectors = &boiler_core::detectors::DETECTORS;
-    let detectors_meta: Vec<_> = detectors
+/// Returns the changes that `update` would make, except for the ones to the
+/// lock file, and the report of the run.
+fn collect_changes(
+    repo: Repo,
+    boiler: &Boiler,
+    options: RunOptions,
+) -> anyhow::Result<(Vec<FileChange>, RunReport)> {
+    let sink = Arc::new(MemoryFileSink::new());
+    let options = RunOptions {
+        sink: sink.clone(),
+        ..options
+    };
+    let report = boiler.run(repo.clone(), &options)?;
+    let changes = sink
+        .changes(&repo)?
+        .into_iter()
+        .filter(|change| change.path() != Path::new(LOCK_FILENAME))
+        .collect();
+
+    Ok((changes, report))
+}
+
+/// An error that happened after the actions were run, such as a merge
+/// conflict, along with the report of the run.
+#[derive(Debug)]
+struct RunFailed {
+    report: RunReport,
+    error: anyhow::Error,
+}
+
+impl RunFailed {
+    /// Returns `report`, or the error with the report attached if `result`
+    /// has failed.
+    fn attach(report: RunReport, result: anyhow::Result<()>) -> anyhow::Result<RunReport> {
+        match result {
+            Ok(()) => Ok(report),
+            Err(error) => Err(Self { report, error }.into()),
+        }
+    }
+}
+
+impl std::fmt::Display for RunFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for RunFailed {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Updates the repository, committing the changes if `commit` is set, and
+/// returns the report of the run.
+///
+/// The report of the changes is written to `out`, so that the output of the
+/// repositories updated concurrently does not get interleaved. If the update
+/// fails after the actions were run, the error is a [`RunFailed`].
+fn update(
+    repo: Repo,
+    boiler: &Boiler,
+    options: RunOptions,
+    dry_run: bool,
+    commit: Option<&CommitOptions>,
+    out: &mut String,
+) -> anyhow::Result<RunReport> {
+    if dry_run {
+        return update_dry_run(repo, boiler, options, out);
+    }
+
+    if commit.is_some_and(|commit| !commit.allow_dirty) {
+        git::ensure_clean(&repo).context(
+            "Refusing to commit on a dirty working tree; pass --allow-dirty to proceed anyway",
+        )?;
+    }
+
+    let report = boiler.run(repo.clone(), &options)?;
+    let result = finish_update(&repo, &report, commit, out);
+
+    RunFailed::attach(report, result)
+}
+
+/// Reports what the update did and commits the changes if `commit` is set.
+fn finish_update(
+    repo: &Repo,
+    report: &RunReport,
+    commit: Option<&CommitOptions>,
+    out: &mut String,
+) -> anyhow::Result<()> {
+    write_summary(report, out)?;
+    report_pruned(&report.pruned, out)?;
+    report_conflicts(&report.conflicts, out)?;
+
+    if let Some(commit) = commit {
+        match git::commit_changes(repo, report, commit.branch.as_deref())? {
+            Some(commit_id) => cwriteln!(
+                out,
+                "<strong>Committed the changes as {}</strong>",
+                commit_id.to_hex_with_len(7)
+            )?,
+            None => cwriteln!(out, "<strong>No changes to commit</strong>")?,
+        }
+    }
+
+    Ok(())
+}
+
+fn update_dry_run(
+    repo: Repo,
+    boiler: &Boiler,
+    options: RunOptions,
+    out: &mut String,
+) -> anyhow::Result<RunReport> {
+    let (changes, report) = collect_changes(repo, boiler, options)?;
+    if changes.is_empty() {
+        cwriteln!(out, "<strong>No changes</strong>")?;
+    }
+    for change in &changes {
+        write_diff(change, out)?;
+    }
+    let result = report_conflicts(&report.conflicts, out);
+
+    RunFailed::attach(report, result)
+}
+
+/// Writes what each action did, along with the time it took.
+fn write_summary(report: &RunReport, out: &mut String) -> std::fmt::Result {
+    let max_len = report
+        .actions
+        .iter()
+        .map(|action| action.action.len())
+        .max()
+        .unwrap_or(0);
+
+    cwriteln!(out, "<strong><underline>Actions:</underline></strong>\n")?;
+    for action in &report.actions {
+        let name = format!("{:<width$}", action.action, width = max_len);
+        match action.status {
+            ActionStatus::Ran => cwriteln!(
+                out,
+                "  <strong>{}</strong>  ran in {:.2?}",
+                name,
+                action.duration
+            )?,
+            ActionStatus::Skipped => {
+                cwriteln!(out, "  <strong>{}</strong>  <dim>skipped</dim>", name)?
+            }
+            ActionStatus::Disabled => {
+                cwriteln!(out, "  <strong>{}</strong>  <dim>disabled</dim>", name)?;
+            }
+        }
+        for file in &action.files {
+            match file.status {
+                FileStatus::Created => {
+                    cwriteln!(out, "    <green>created</green>    {}", file.path.display())?;
+                }
+                FileStatus::Updated => {
+                    cwriteln!(
+                        out,
+                        "    <yellow>updated</yellow>    {}",
+                        file.path.display()
+                    )?;
+                }
+                FileStatus::Unchanged => {
+                    cwriteln!(out, "    <dim>unchanged</dim>  {}", file.path.display())?;
+                }
+            }
+        }
+    }
+    cwriteln!(out, "\nFinished in {:.2?}\n", report.duration)
+}
+
+/// Updates all the repositories using `jobs` threads, continuing past the
+/// failures, and prints a summary of the results.
+fn update_batch(
+    repo_paths: &[PathBuf],
+    boiler: &Boiler,
+    options: &RunOptions,
+    dry_run: bool,
+    commit: Option<&CommitOptions>,
+    jobs: usize,
+    json: bool,
+) -> anyhow::Result<()> {
+    let next_index = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<anyhow::Result<RunReport>>>> =
+        Mutex::new(repo_paths.iter().map(|_| None).collect());
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            scope.spawn(|| loop {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                let Some(repo_path) = repo_paths.get(index) else {
+                    break;
+                };
+
+                let mut out = String::new();
+                let result = update(
+                    Repo::new(repo_path.clone()),
+                    boiler,
+                    options.clone(),
+                    dry_run,
+                    commit,
+                    &mut out,
+                );
+                if let Err(error) = &result {
+                    out.push_str(&format!("Error: {error:?}\n"));
+                }
+                if !json {
+                    cprintln!(
+                        "<strong><underline>{}:</underline></strong>\n\n{}",
+                        repo_path.display(),
+                        out
+                    );
+                }
+
+                results.lock().expect("results lock poisoned")[index] = Some(result);
+            });
+        }
+    });
+
+    let results: Vec<_> = repo_paths
+        .iter()
+        .zip(results.into_inner().expect("results lock poisoned"))
+        .map(|(repo_path, result)| (repo_path, result.expect("repository not processed")))
+        .collect();
+    if json {
+        print_batch_json(&results)?;
+    } else {
+        print_batch_summary(&results);
+    }
+
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    if failed > 0 {
+        anyhow::bail!("{failed} of {} repositories failed", results.len());
+    }
+
+    Ok(())
+}
+
+/// Prints an array with the report of each repository, with the error if it
+/// failed; the repositories that failed before the actions were run only get
+/// the error.
+fn print_batch_json(results: &[(&PathBuf, anyhow::Result<RunReport>)]) -> anyhow::Result<()> {
+    let entries: Vec<_> = results
+        .iter()
+        .map(|(repo_path, result)| match result {
+            Ok(report) => serde_json::to_value(report),
+            Err(error) => match error.downcast_ref::<RunFailed>() {
+                Some(failed) => serde_json::to_value(&failed.report).map(|mut entry| {
+                    entry["error"] = serde_json::json!(format!("{error:#}"));
+                    entry
+                }),
+                None => Ok(serde_json::json!({
+                    "repo": repo_path,
+                    "error": format!("{error:#}"),
+                })),
+            },
+        })
+        .collect::<Result<_, _>>()?;
+    println!("{}", serde_json::to_string_pretty(&entries)?);
+
+    Ok(())
+}
+
+fn print_batch_summary(results: &[(&PathBuf, anyhow::Result<RunReport>)]) {
+    let max_len = results
+        .iter()
+        .map(|(repo_path, _)| repo_path.display().to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    cprintln!("<strong><underline>Summary:</underline></strong>\n");
+    for (repo_path, result) in results {
+        let repo_path = format!("{:<width$}", repo_path.display(), width = max_len);
+        match result {
+            Ok(report) if !report.has_changes() => {
+                cprintln!("  <strong>{}</strong>  <green>unchanged</green>", repo_path);
+            }
+            Ok(report) => cprintln!(
+                "  <strong>{}</strong>  <yellow>changed</yellow> ({} file(s))",
+                repo_path,
+                report.changed().len() + report.pruned.len()
+            ),
+            Err(error) => cprintln!(
+                "  <strong>{}</strong>  <red>failed</red> ({:#})",
+                repo_path,
+                error
+            ),
+        }
+    }
+}
+
+/// Returns the git repositories found directly in `dir`, sorted by path.
+fn find_repos(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut repo_paths = Vec::new();
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Could not read directory {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() && path.join(".git").exists() {
+            repo_paths.push(path);
+        }
+    }
+    repo_paths.sort();
+
+    Ok(repo_paths)
+}
+
+fn check(repo: Repo, boiler: &Boiler, options: RunOptions) -> anyhow::Result<()> {
+    let (changes, _report) = collect_changes(repo, boiler, options)?;
+    if changes.is_empty() {
+        cprintln!("<strong>All files are up to date</strong>");
+        return Ok(());
+    }
+
+    cprintln!("<strong><underline>Files out of date:</underline></strong>\n");
+    for change in &changes {
+        let status = if change.is_new_file() {
+            "missing"
+        } else if change.is_removed() {
+            "stale"
+        } else {
+            "modified"
+        };
+        cprintln!(
+            "  <strong>{}</strong> [{}]",
+            change.path().display(),
+            status
+        );
+    }
+
+    anyhow::bail!(
+        "{} file(s) differ from the generated ones; run `boiler update` to fix them",
+        changes.len()
+    )
+}
+
+fn report_pruned(pruned: &[PathBuf], out: &mut String) -> anyhow::Result<()> {
+    if pruned.is_empty() {
+        return Ok(());
+    }
+
+    cwriteln!(out, "<strong><underline>Removed:</underline></strong>\n")?;
+    for path in pruned {
+        cwriteln!(out, "  <strong>{}</strong>", path.display())?;
+    }
+
+    Ok(())
+}
+
+fn report_conflicts(conflicts: &[PathBuf], out: &mut String) -> anyhow::Result<()> {
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+
+    cwriteln!(out, "<strong><underline>Conflicts:</underline></strong>\n")?;
+    for path in conflicts {
+        cwriteln!(out, "  <strong>{}</strong>", path.display())?;
+    }
+
+    anyhow::bail!(
+        "{} file(s) have local changes conflicting with the generated content; resolve the \
+         conflict markers in them",
+        conflicts.len()
+    )
+}
+
+fn init(repo: &Repo, boiler: &Boiler, force: bool) -> anyhow::Result<()> {
+    let path = repo.path().join(REPO_CONFIG_FILENAME);
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists; pass --force to overwrite it",
+            path.display()
+        );
+    }
+
+    let config = scaffold_repo_config(boiler, repo)?;
+    std::fs::write(&path, config).with_context(|| format!("Could not write {}", path.display()))?;
+    cprintln!("Created <strong>{}</strong>", path.display());
+
+    Ok(())
+}
+
+fn explain(repo: &Repo, boiler: &Boiler, key: &str) -> anyhow::Result<()> {
+    let (context, provenance) = boiler.build_context_with_provenance(repo, ContextStage::Final)?;
+    let Some(value) = context.get_path(key) else {
+        anyhow::bail!("Key not found in the context: {key}");
+    };
+
+    cprintln!("<strong><underline>Value:</underline></strong>\n");
+    for line in value.as_yaml().lines() {
+        println!("  {line}");
+    }
+
+    cprintln!("\n<strong><underline>Set by:</underline></strong>\n");
+    for (path, sources) in provenance.get(key) {
+        for source in sources {
+            cprintln!("  <strong>{}</strong>  {}", path, source);
+        }
+    }
+
+    Ok(())
+}
+
+fn write_diff(change: &FileChange, out: &mut String) -> std::fmt::Result {
+    for line in change.unified_diff().lines() {
+        if line.starts_with("---") || line.starts_with("+++") {
+            cwriteln!(out, "<strong>{}</strong>", line)?;
+        } else if line.starts_with("@@") {
+            cwriteln!(out, "<cyan>{}</cyan>", line)?;
+        } else if line.starts_with('+') {
+            cwriteln!(out, "<green>{}</green>", line)?;
+        } else if line.starts_with('-') {
+            cwriteln!(out, "<red>{}</red>", line)?;
+        } else {
+            writeln!(out, "{line}")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn list_detectors(boiler: &Boiler) {
+    let detectors_meta: Vec<_> = boiler
+        .detectors()
         .iter()
         .map(|detector| {
             (
@@ -89,8 +844,15 @@ fn list_detectors() {
     print_functions("Detectors", &detectors_meta);
 }
 
-fn list_actions() {
-    let actions = &boiler_core::actions::ACTIONS;
+fn list_actions(repo: &Repo, boiler: &Boiler) -> anyhow::Result<()> {
+    let custom_actions: Vec<_> = boiler
+        .repo_config(repo)?
+        .custom_actions()
+        .iter()
+        .cloned()
+        .map(CustomAction::new)
+        .collect();
+    let actions = boiler.actions_with_custom(&custom_actions)?;
     let actions_meta: Vec<_> = actions
         .iter()
         .map(|action| {
@@ -103,6 +865,8 @@ fn list_actions() {
         .collect();
 
     print_functions("Actions", &actions_meta);
+
+    Ok(())
 }
 
 fn print_functions(section_name: &str, functions: &[(&str, &str, bool)]) {
@@ -0,0 +1,108 @@
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+
+use assert_cmd::Command;
+use assert_fs::fixture::{FileWriteStr, PathChild};
+use assert_fs::TempDir;
+
+const GO_DETECTOR: &str = r#"#!/bin/sh
+if [ "$1" = --description ]; then
+    echo "Go"
+else
+    cat > /dev/null
+    echo '{"langs": ["go"]}'
+fi
+"#;
+
+fn boiler(home: &TempDir, plugins_dir: &TempDir) -> Command {
+    let mut command = Command::cargo_bin("boiler").unwrap();
+    command
+        .env("HOME", home.path())
+        .env("PATH", "")
+        .arg("--plugins-dir")
+        .arg(plugins_dir.path());
+    command
+}
+
+fn write_go_detector(plugins_dir: &TempDir) {
+    let plugin = plugins_dir.child("boiler-detector-go");
+    plugin.write_str(GO_DETECTOR).unwrap();
+    std::fs::set_permissions(plugin.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn test_context_plugin() {
+    let home = TempDir::new().unwrap();
+    let plugins_dir = TempDir::new().unwrap();
+    write_go_detector(&plugins_dir);
+    let repo = TempDir::new().unwrap();
+
+    let output = boiler(&home, &plugins_dir)
+        .arg("context")
+        .arg("--repo")
+        .arg(repo.path())
+        .arg("--format")
+        .arg("json")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let context: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(context["langs"], serde_json::json!(["go"]));
+}
+
+#[test]
+fn test_context_plugin_excluded() {
+    let home = TempDir::new().unwrap();
+    let plugins_dir = TempDir::new().unwrap();
+    write_go_detector(&plugins_dir);
+    let repo = TempDir::new().unwrap();
+    repo.child(".boiler.yml")
+        .write_str("detectors_excluded: [Go]\n")
+        .unwrap();
+
+    let output = boiler(&home, &plugins_dir)
+        .arg("context")
+        .arg("--repo")
+        .arg(repo.path())
+        .arg("--format")
+        .arg("json")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let context: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_ne!(context["langs"], serde_json::json!(["go"]));
+}
+
+#[test]
+fn test_update_json_conflict() {
+    let home = TempDir::new().unwrap();
+    let plugins_dir = TempDir::new().unwrap();
+    let repo = TempDir::new().unwrap();
+    repo.child("Cargo.toml")
+        .write_str("[package]\nname = \"test\"\n")
+        .unwrap();
+    let update = || {
+        boiler(&home, &plugins_dir)
+            .arg("update")
+            .arg("--repo")
+            .arg(repo.path())
+            .arg("--only")
+            .arg("RustfmtToml")
+            .arg("--json")
+            .output()
+            .unwrap()
+    };
+    assert!(update().status.success());
+    // Outside of a git repository, the previously generated content is not
+    // kept, so any local change conflicts with the generated content
+    repo.child("rustfmt.toml").write_str("local\n").unwrap();
+
+    let output = update();
+
+    assert!(!output.status.success(), "{output:?}");
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["conflicts"], serde_json::json!(["rustfmt.toml"]));
+}
@@ -1,22 +1,24 @@
 use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Context;
-use dependabot_config::DependabotConfigAction;
 use log::debug;
 use once_cell::sync::Lazy;
-use pre_commit_config::PreCommitConfigAction;
-use readme::ReadmeAction;
-use rust_ci::RustCiAction;
-use rustfmt_toml::RustfmtTomlAction;
 use serde::{Deserialize, Serialize};
+use tera::Tera;
 
-use crate::actions::docker::DockerCiAction;
-use crate::actions::license::LicenseAction;
-use crate::actions::pre_commit_ci::PreCommitCiAction;
-use crate::actions::python::PythonCiAction;
+use crate::actions_utils;
+use crate::context::RepoConfigError;
 use crate::data::{Repo, Value};
+use crate::file_sink::{ActionIoError, DiskFileSink, FileSink};
 use crate::function_meta::{FunctionEnabled, FunctionMeta};
+use crate::lock::{Lock, LockRecorder, OutputStore};
+use crate::report::{ActionReport, ActionStatus};
+use crate::template_renderer::{self, TERA};
 
+mod custom;
 mod dependabot_config;
 mod docker;
 mod license;
@@ -27,58 +29,196 @@ mod readme;
 mod rust_ci;
 mod rustfmt_toml;
 
+pub use custom::CustomAction;
+
 #[derive(Debug)]
 pub struct ActionData {
     pub repo: Repo,
     pub context: Value,
+    pub sink: Arc<dyn FileSink>,
+    pub tera: Arc<Tera>,
+    pub lock: Arc<LockRecorder>,
+    /// The contents generated by the previous runs, used to merge the changes
+    /// made by the user to the generated files.
+    pub outputs: OutputStore,
+    /// Whether the changes made to the generated files by the user are kept.
+    pub keep_local_changes: bool,
 }
 
 impl ActionData {
     #[must_use]
     pub fn new(repo: Repo, context: Value) -> Self {
-        Self { repo, context }
+        Self {
+            repo,
+            context,
+            sink: Arc::new(DiskFileSink),
+            tera: TERA.clone(),
+            lock: Arc::new(LockRecorder::default()),
+            outputs: OutputStore::default(),
+            keep_local_changes: true,
+        }
+    }
+
+    #[must_use]
+    pub fn with_sink(mut self, sink: Arc<dyn FileSink>) -> Self {
+        self.sink = sink;
+        self
+    }
+
+    #[must_use]
+    pub fn with_tera(mut self, tera: Arc<Tera>) -> Self {
+        self.tera = tera;
+        self
+    }
+
+    /// Sets the lock of the previous run, used to merge the changes made by
+    /// the user to the generated files.
+    #[must_use]
+    pub fn with_lock(mut self, lock: Lock) -> Self {
+        self.lock = Arc::new(LockRecorder::new(lock));
+        self
+    }
+
+    #[must_use]
+    pub fn with_outputs(mut self, outputs: OutputStore) -> Self {
+        self.outputs = outputs;
+        self
+    }
+
+    #[must_use]
+    pub fn with_keep_local_changes(mut self, keep_local_changes: bool) -> Self {
+        self.keep_local_changes = keep_local_changes;
+        self
+    }
+
+    /// Writes the file at `path`, relative to the repository root, generated
+    /// from `template`.
+    ///
+    /// Like the files written by the built-in actions, the file is recorded in
+    /// the lock, and the changes made to it by the user are kept.
+    pub fn write_file<T: Into<PathBuf>>(
+        &self,
+        template: &str,
+        path: T,
+        content: &str,
+    ) -> Result<(), ActionIoError> {
+        actions_utils::write_file(self, template, path, content)
+    }
+
+    /// Renders `template` to the file at `output`, relative to the repository
+    /// root.
+    ///
+    /// `template` is either the name of a template known to the template
+    /// engine, or a path to a template file relative to the repository root.
+    pub fn render_template(&self, template: &str, output: &Path) -> ActionResult {
+        template_renderer::render_template_to(template, output, self)?;
+        Ok(())
     }
 }
 
-type ActionResult = anyhow::Result<()>;
+pub type ActionResult = anyhow::Result<()>;
 
 pub trait Action: FunctionMeta + Send + Sync {
     fn run(&self, data: &ActionData) -> ActionResult;
 }
 
-pub static ACTIONS: Lazy<[&dyn Action; 9]> = Lazy::new(|| {
-    [
-        &DependabotConfigAction,
-        &DockerCiAction,
-        &LicenseAction,
-        &PreCommitCiAction,
-        &PreCommitConfigAction,
-        &PythonCiAction,
-        &ReadmeAction,
-        &RustCiAction,
-        &RustfmtTomlAction,
-    ]
+/// An action registered with the [`boiler_macros::action`] attribute.
+#[derive(Debug)]
+pub struct ActionRegistration {
+    create: fn() -> Arc<dyn Action>,
+}
+
+impl ActionRegistration {
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn new(create: fn() -> Arc<dyn Action>) -> Self {
+        Self { create }
+    }
+}
+
+inventory::collect!(ActionRegistration);
+
+/// All the registered actions, including the ones defined in the other crates
+/// linked in, sorted by name.
+pub static ACTIONS: Lazy<Vec<Arc<dyn Action>>> = Lazy::new(|| {
+    let mut actions: Vec<_> = inventory::iter::<ActionRegistration>
+        .into_iter()
+        .map(|registration| (registration.create)())
+        .collect();
+    actions.sort_by(|a, b| a.name().cmp(b.name()));
+    actions
 });
 
-pub fn run_actions(action_data: &ActionData, actions_enabled: &FunctionEnabled) -> ActionResult {
-    for action in *ACTIONS {
-        if actions_enabled.is_enabled(action.name()) {
+/// Returns `actions` followed by `custom_actions`.
+pub fn with_custom_actions<'a>(
+    actions: &'a [Arc<dyn Action>],
+    custom_actions: &'a [CustomAction],
+) -> Result<Vec<&'a dyn Action>, RepoConfigError> {
+    let mut actions: Vec<&dyn Action> = actions.iter().map(AsRef::as_ref).collect();
+    for custom_action in custom_actions {
+        if actions
+            .iter()
+            .any(|action| action.name() == custom_action.name())
+        {
+            return Err(RepoConfigError::DuplicateActionName(ActionName::new(
+                custom_action.name().to_owned(),
+            )));
+        }
+        actions.push(custom_action);
+    }
+
+    Ok(actions)
+}
+
+/// Runs the enabled actions one by one and reports what each of them did.
+pub fn run_actions(
+    actions: &[&dyn Action],
+    action_data: &ActionData,
+    actions_enabled: &FunctionEnabled,
+) -> anyhow::Result<Vec<ActionReport>> {
+    let mut reports = Vec::new();
+
+    for action in actions {
+        let start = Instant::now();
+        let (status, files) = if actions_enabled.is_enabled(action.name()) {
             debug!("Running action: {}", action.name());
+            let files_before = action_data.lock.files().len();
             action
                 .run(action_data)
                 .with_context(|| format!("Failed to run action: {}", action.name()))?;
+
+            let files = action_data.lock.files().split_off(files_before);
+            let status = if files.is_empty() {
+                ActionStatus::Skipped
+            } else {
+                ActionStatus::Ran
+            };
+            (status, files)
         } else {
             debug!("Action disabled: {}", action.name());
-        }
+            (ActionStatus::Disabled, Vec::new())
+        };
+
+        reports.push(ActionReport {
+            action: action.name().to_owned(),
+            status,
+            files,
+            duration: start.elapsed(),
+        });
     }
 
-    Ok(())
+    Ok(reports)
 }
 
 pub fn create_actions_enabled() -> FunctionEnabled {
+    let actions: Vec<_> = ACTIONS.iter().map(AsRef::as_ref).collect();
+    create_actions_enabled_for(&actions)
+}
+
+pub fn create_actions_enabled_for(actions: &[&dyn Action]) -> FunctionEnabled {
     let mut actions_enabled = FunctionEnabled::new();
 
-    for action in ACTIONS.iter() {
+    for action in actions {
         actions_enabled.set_enabled(action.name().to_owned(), action.default_enabled());
     }
 
@@ -106,3 +246,77 @@ impl Display for ActionName {
         write!(f, "{}", self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::actions::python::PythonCiAction;
+    use crate::actions::rust_ci::RustCiAction;
+    use crate::actions::rustfmt_toml::RustfmtTomlAction;
+    use crate::actions::{create_actions_enabled_for, run_actions, Action, ActionData, ACTIONS};
+    use crate::context_keys;
+    use crate::data::Value;
+    use crate::report::{ActionStatus, FileReport, FileStatus};
+    use crate::test_utils::TempRepo;
+
+    #[test]
+    fn test_run_actions() {
+        let repo = TempRepo::new();
+        let action_data = ActionData::new(
+            repo.repo(),
+            Value::new_object([(
+                context_keys::LANGS.to_owned(),
+                Value::new_array([Value::new_string("rust")]),
+            )]),
+        );
+        let actions: [&dyn Action; 3] = [&PythonCiAction, &RustCiAction, &RustfmtTomlAction];
+        let mut actions_enabled = create_actions_enabled_for(&actions);
+        actions_enabled.set_enabled("RustCi".to_owned(), false);
+
+        let reports = run_actions(&actions, &action_data, &actions_enabled).unwrap();
+
+        let statuses: Vec<_> = reports
+            .iter()
+            .map(|report| (report.action.as_str(), report.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("PythonCi", ActionStatus::Skipped),
+                ("RustCi", ActionStatus::Disabled),
+                ("RustfmtToml", ActionStatus::Ran),
+            ]
+        );
+        assert_eq!(
+            reports[2].files,
+            [FileReport::new(
+                Path::new("rustfmt.toml"),
+                FileStatus::Created
+            )]
+        );
+
+        let reports = run_actions(&actions, &action_data, &actions_enabled).unwrap();
+        assert_eq!(reports[2].files[0].status, FileStatus::Unchanged);
+    }
+
+    #[test]
+    fn test_actions_registered() {
+        let names: Vec<_> = ACTIONS.iter().map(|action| action.name()).collect();
+
+        assert_eq!(
+            names,
+            [
+                "DependabotConfig",
+                "DockerCi",
+                "License",
+                "PreCommitCi",
+                "PreCommitConfig",
+                "PythonCi",
+                "Readme",
+                "RustCi",
+                "RustfmtToml",
+            ]
+        );
+    }
+}
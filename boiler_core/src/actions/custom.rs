@@ -0,0 +1,157 @@
+use crate::actions::{Action, ActionData, ActionResult};
+use crate::context::CustomActionConfig;
+use crate::function_meta::FunctionMeta;
+use crate::template_renderer::{self, StringRenderer};
+
+/// Renders a single template, as declared in the config.
+#[derive(Debug, Clone)]
+pub struct CustomAction {
+    config: CustomActionConfig,
+    description: String,
+}
+
+impl CustomAction {
+    #[must_use]
+    pub fn new(config: CustomActionConfig) -> Self {
+        let description = config.description().map_or_else(
+            || {
+                format!(
+                    "Renders {} to {}",
+                    config.template(),
+                    config.output().display()
+                )
+            },
+            ToOwned::to_owned,
+        );
+
+        Self {
+            config,
+            description,
+        }
+    }
+}
+
+impl FunctionMeta for CustomAction {
+    fn name(&self) -> &str {
+        self.config.name().name()
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn default_enabled(&self) -> bool {
+        true
+    }
+}
+
+impl Action for CustomAction {
+    fn run(&self, data: &ActionData) -> ActionResult {
+        let mut renderer = StringRenderer::new(data);
+        if let Some(condition) = self.config.condition() {
+            if !template_renderer::evaluate_condition(condition, &mut renderer)? {
+                return Ok(());
+            }
+        }
+
+        template_renderer::render_template_with(
+            self.config.template(),
+            self.config.output(),
+            &mut renderer,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::actions::custom::CustomAction;
+    use crate::actions::{Action, ActionData};
+    use crate::context::CustomActionConfig;
+    use crate::context_keys;
+    use crate::data::Value;
+    use crate::test_utils::TempRepo;
+
+    fn custom_action(yaml: &str) -> CustomAction {
+        let config: CustomActionConfig = serde_yaml::from_str(yaml).unwrap();
+        CustomAction::new(config)
+    }
+
+    fn go_action_data(repo: &TempRepo) -> ActionData {
+        ActionData::new(
+            repo.repo(),
+            Value::new_object([(
+                context_keys::LANGS.to_owned(),
+                Value::new_array([Value::new_string("go")]),
+            )]),
+        )
+    }
+
+    #[test]
+    fn test_template_from_repo() {
+        let repo = TempRepo::new();
+        repo.write_str(
+            "templates/go.yml.j2",
+            "langs: {{ boiler.langs | to_yaml_array }}\n",
+        );
+        let action = custom_action(
+            "name: GoCi\ntemplate: templates/go.yml.j2\noutput: .github/workflows/go.yml\n",
+        );
+
+        action.run(&go_action_data(&repo)).unwrap();
+
+        assert_eq!(repo.read_str(".github/workflows/go.yml"), "langs: [go]\n");
+    }
+
+    #[test]
+    fn test_built_in_template() {
+        let repo = TempRepo::new();
+        let action = custom_action("name: Rustfmt\ntemplate: rustfmt.toml.j2\noutput: a.toml\n");
+
+        action.run(&go_action_data(&repo)).unwrap();
+
+        assert!(repo.file_not_empty("a.toml"));
+    }
+
+    #[test]
+    fn test_condition() {
+        let repo = TempRepo::new();
+        repo.write_str("go.j2", "go\n");
+        repo.write_str("rust.j2", "rust\n");
+        let go_action = custom_action(
+            "name: Go\ntemplate: go.j2\noutput: go.txt\ncondition: \"'go' in boiler.langs\"\n",
+        );
+        let rust_action = custom_action(
+            "name: Rust\ntemplate: rust.j2\noutput: rust.txt\ncondition: \"'rust' in boiler.langs\"\n",
+        );
+
+        go_action.run(&go_action_data(&repo)).unwrap();
+        rust_action.run(&go_action_data(&repo)).unwrap();
+
+        assert_eq!(repo.read_str("go.txt"), "go\n");
+        assert!(!repo.path().join("rust.txt").exists());
+    }
+
+    #[test]
+    fn test_condition_filters() {
+        let repo = TempRepo::new();
+        repo.write_str("go.j2", "go\n");
+        let action = custom_action(
+            "name: Go\ntemplate: go.j2\noutput: go.txt\ncondition: \"boiler.langs | to_yaml_array == '[go]'\"\n",
+        );
+
+        action.run(&go_action_data(&repo)).unwrap();
+
+        assert_eq!(repo.read_str("go.txt"), "go\n");
+    }
+
+    #[test]
+    fn test_description() {
+        let action = custom_action("name: Go\ntemplate: go.j2\noutput: go.txt\n");
+        assert_eq!(action.description.as_str(), "Renders go.j2 to go.txt");
+
+        let action =
+            custom_action("name: Go\ndescription: Go files\ntemplate: go.j2\noutput: go.txt\n");
+        assert_eq!(action.description.as_str(), "Go files");
+    }
+}
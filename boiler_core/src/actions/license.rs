@@ -1,9 +1,10 @@
-use boiler_macros::FunctionMeta;
+use boiler_macros::{action, FunctionMeta};
 
 use crate::actions::{Action, ActionData, ActionResult};
 use crate::{context_keys, template_renderer};
 
 /// Generates the LICENSE file, updating year or author if necessary.
+#[action]
 #[derive(Debug, FunctionMeta)]
 pub struct LicenseAction;
 
@@ -1,9 +1,10 @@
-use boiler_macros::FunctionMeta;
+use boiler_macros::{action, FunctionMeta};
 
 use crate::actions::{Action, ActionData, ActionResult};
 use crate::template_renderer;
 
 /// Generates a pre-commit configuration file.
+#[action]
 #[derive(Debug, FunctionMeta)]
 pub struct PreCommitConfigAction;
 
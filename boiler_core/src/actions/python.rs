@@ -1,10 +1,11 @@
-use boiler_macros::FunctionMeta;
+use boiler_macros::{action, FunctionMeta};
 
 use crate::actions::{Action, ActionData, ActionResult};
 use crate::detectors_utils::ActionDataExt;
 use crate::template_renderer;
 
 /// Generates a Python CI configuration file for GitHub Actions.
+#[action]
 #[derive(Debug, FunctionMeta)]
 pub struct PythonCiAction;
 
@@ -1,18 +1,23 @@
 use anyhow::Context;
-use boiler_macros::FunctionMeta;
+use boiler_macros::{action, FunctionMeta};
 use log::warn;
 use regex::Regex;
 
 use crate::actions::{Action, ActionData, ActionResult};
 use crate::actions_utils::write_file;
-use crate::template_renderer::{build_template_renderer_context, TERA};
+use crate::managed_regions::find_regions;
+use crate::template_renderer::build_template_renderer_context;
 
 /// Updated the README.md file header with badges.
+#[action]
 #[derive(Debug, FunctionMeta)]
 pub struct ReadmeAction;
 
 const README_FILENAME: &str = "README.md";
 const README_HEADER_TEMPLATE: &str = "README.header.md.j2";
+const README_HEADER_REGION: &str = "header";
+const README_HEADER_BEGIN_MARKER: &str = "<!-- boiler:begin header -->";
+const README_HEADER_END_MARKER: &str = "<!-- boiler:end -->";
 
 impl Action for ReadmeAction {
     fn run(&self, data: &ActionData) -> ActionResult {
@@ -25,24 +30,36 @@ impl Action for ReadmeAction {
             String::new()
         };
 
-        // Match the top-level header, empty lines, and all the badges at the top of the
-        // README
-        let header_regex = Regex::new(r"(?m)(?:^.+\n=+\n|^# .+\n|^\s*\n|^\[!.+\)\n)*").unwrap();
-        if let Some(captures) = header_regex.captures(&readme) {
-            let header_end = captures.get(0).unwrap().end();
-            readme = readme[header_end..].to_string();
-        }
-
-        let output = TERA
+        let output = data
+            .tera
             .render(
                 README_HEADER_TEMPLATE,
                 &build_template_renderer_context(data),
             )
             .with_context(|| format!("could not render {README_HEADER_TEMPLATE}"))?;
-        readme = format!("{output}\n\n{readme}");
-        let readme = readme.trim().to_owned() + "\n";
+        let header = format!("{README_HEADER_BEGIN_MARKER}\n{output}\n{README_HEADER_END_MARKER}");
+
+        let has_header_region = find_regions(&readme)
+            .with_context(|| format!("invalid managed regions in {README_FILENAME}"))?
+            .iter()
+            .any(|region| region.id() == README_HEADER_REGION);
+        let readme = if has_header_region {
+            // Only the header region is replaced; the rest is kept by write_file
+            format!("{header}\n")
+        } else {
+            // Match the top-level header, empty lines, and all the badges at the top of the
+            // README, as generated before the header was marked as a managed region
+            let header_regex = Regex::new(r"(?m)(?:^.+\n=+\n|^# .+\n|^\s*\n|^\[!.+\)\n)*").unwrap();
+            if let Some(captures) = header_regex.captures(&readme) {
+                let header_end = captures.get(0).unwrap().end();
+                readme = readme[header_end..].to_string();
+            }
+
+            let readme = format!("{header}\n\n{readme}");
+            readme.trim().to_owned() + "\n"
+        };
 
-        write_file(&data.repo, README_FILENAME, &readme)?;
+        write_file(data, README_HEADER_TEMPLATE, README_FILENAME, &readme)?;
 
         Ok(())
     }
@@ -50,6 +67,8 @@ impl Action for ReadmeAction {
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
     use crate::actions::readme::{ReadmeAction, README_FILENAME};
     use crate::actions::{Action, ActionData};
     use crate::context_keys;
@@ -81,17 +100,60 @@ This is a very useful tool!"#);
         assert!(repo.file_not_empty(README_FILENAME));
         assert_eq!(
             repo.read_str(README_FILENAME),
-            r#"Example Project
+            r#"<!-- boiler:begin header -->
+Example Project
 ===============
 
 [![Rust Build Status](https://github.com/m4tx/boiler/workflows/Rust%20CI/badge.svg)](https://github.com/m4tx/boiler/actions/workflows/rust.yml)
 [![MIT licensed](https://img.shields.io/github/license/m4tx/boiler)](https://github.com/m4tx/boiler/blob/master/LICENSE)
+<!-- boiler:end -->
 
 This is a very useful tool!
 "#
         );
     }
 
+    #[test]
+    fn test_overwrite_region() {
+        let repo = TempRepo::new();
+        repo.write_str(
+            README_FILENAME,
+            "Intro\n\n<!-- boiler:begin header -->\nOld\n===\n<!-- boiler:end -->\n\n# Usage\n\nText\n",
+        );
+
+        let action_data = get_test_action_data(&repo);
+
+        ReadmeAction.run(&action_data).unwrap();
+
+        let readme = repo.read_str(README_FILENAME);
+        assert!(readme.starts_with("Intro\n\n<!-- boiler:begin header -->\nExample Project\n"));
+        assert!(readme.ends_with("<!-- boiler:end -->\n\n# Usage\n\nText\n"));
+        let lock = action_data.lock.current();
+        assert!(!lock
+            .get(Path::new(README_FILENAME))
+            .unwrap()
+            .is_modified(&readme));
+    }
+
+    #[test]
+    fn test_insert_header_region() {
+        let repo = TempRepo::new();
+        repo.write_str(
+            README_FILENAME,
+            "# Example Project\n\n<!-- boiler:begin usage -->\nUsage\n<!-- boiler:end -->\n",
+        );
+
+        let action_data = get_test_action_data(&repo);
+
+        ReadmeAction.run(&action_data).unwrap();
+
+        let readme = repo.read_str(README_FILENAME);
+        assert!(readme.starts_with("<!-- boiler:begin header -->\nExample Project\n"));
+        assert!(readme.ends_with(
+            "<!-- boiler:end -->\n\n<!-- boiler:begin usage -->\nUsage\n<!-- boiler:end -->\n"
+        ));
+    }
+
     fn get_test_action_data(repo: &TempRepo) -> ActionData {
         ActionData::new(
             repo.repo(),
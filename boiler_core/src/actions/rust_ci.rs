@@ -1,10 +1,11 @@
-use boiler_macros::FunctionMeta;
+use boiler_macros::{action, FunctionMeta};
 
 use crate::actions::{Action, ActionData, ActionResult};
 use crate::detectors_utils::ActionDataExt;
 use crate::template_renderer;
 
 /// Generates a Rust CI configuration file for GitHub Actions.
+#[action]
 #[derive(Debug, FunctionMeta)]
 pub struct RustCiAction;
 
@@ -40,7 +41,31 @@ mod tests {
     #[test]
     fn test_generate() {
         let repo = TempRepo::new();
-        let action_data = ActionData::new(
+        let action_data = get_test_action_data(&repo);
+
+        RustCiAction.run(&action_data).unwrap();
+
+        assert!(repo.file_not_empty(RUST_CI_FILENAME));
+    }
+
+    #[test]
+    fn test_keep_user_jobs() {
+        let repo = TempRepo::new();
+        repo.write_str(
+            RUST_CI_FILENAME,
+            "# boiler:begin rust-ci\nname: Old\n# boiler:end\n  custom:\n    runs-on: ubuntu-latest\n",
+        );
+        let action_data = get_test_action_data(&repo);
+
+        RustCiAction.run(&action_data).unwrap();
+
+        let rust_ci = repo.read_str(RUST_CI_FILENAME);
+        assert!(rust_ci.starts_with("# boiler:begin rust-ci\nname: Rust CI\n"));
+        assert!(rust_ci.ends_with("# boiler:end\n  custom:\n    runs-on: ubuntu-latest\n"));
+    }
+
+    fn get_test_action_data(repo: &TempRepo) -> ActionData {
+        ActionData::new(
             repo.repo(),
             Value::new_object([
                 (
@@ -68,10 +93,6 @@ mod tests {
                     Value::new_array(vec![]),
                 ),
             ]),
-        );
-
-        RustCiAction.run(&action_data).unwrap();
-
-        assert!(repo.file_not_empty(RUST_CI_FILENAME));
+        )
     }
 }
@@ -1,10 +1,11 @@
-use boiler_macros::FunctionMeta;
+use boiler_macros::{action, FunctionMeta};
 
 use crate::actions::{Action, ActionData, ActionResult};
 use crate::detectors_utils::ActionDataExt;
 use crate::template_renderer;
 
 /// Generates a rustfmt configuration file.
+#[action]
 #[derive(Debug, FunctionMeta)]
 pub struct RustfmtTomlAction;
 
@@ -1,62 +1,80 @@
-use std::path::PathBuf;
-
-use log::debug;
-
-use crate::data::Repo;
-
-#[derive(Debug, thiserror::Error)]
-pub enum ActionIoError {
-    #[error("Could not create directory: {path}")]
-    CreateDir {
-        path: PathBuf,
-        #[source]
-        source: std::io::Error,
-    },
-    #[error("Could not write file: {path}")]
-    WriteFile {
-        path: PathBuf,
-        #[source]
-        source: std::io::Error,
-    },
-}
+use std::path::{Path, PathBuf};
 
-impl ActionIoError {
-    #[must_use]
-    fn new_create_dir(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
-        Self::CreateDir {
-            path: path.into(),
-            source,
-        }
-    }
+use log::warn;
 
-    #[must_use]
-    fn new_write_file(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
-        Self::WriteFile {
-            path: path.into(),
-            source,
-        }
-    }
-}
+use crate::actions::ActionData;
+use crate::file_sink::ActionIoError;
+use crate::lock::{merge_user_edits, LockEntry, MergeResult};
+use crate::managed_regions::merge_regions;
+use crate::report::FileStatus;
 
+/// Writes the file at `path`, relative to the repository root, generated from
+/// `template`.
+///
+/// If both the existing file and `content` contain managed regions, only these
+/// are replaced with the ones from `content`; the rest of the file is kept as
+/// is.
+/// Otherwise, the changes made to the file since it was last generated are
+/// merged into `content`, unless [`ActionData::keep_local_changes`] is unset;
+/// the conflicts are recorded in [`ActionData::lock`].
 pub fn write_file<T: Into<PathBuf>>(
-    repo: &Repo,
+    data: &ActionData,
+    template: &str,
     path: T,
     content: &str,
 ) -> Result<(), ActionIoError> {
-    let full_path = repo.path().join(path.into());
-    let parent_dir = full_path.parent().expect("file path has no parent");
-
-    std::fs::create_dir_all(parent_dir)
-        .map_err(|e| ActionIoError::new_create_dir(parent_dir, e))?;
-    if let Ok(old_content) = std::fs::read_to_string(&full_path) {
-        if old_content == content {
-            debug!("File {} unchanged", full_path.display());
-            return Ok(());
-        }
+    let path = path.into();
+    let full_path = data.repo.path().join(&path);
+
+    if !full_path.exists() {
+        data.lock
+            .record(&path, LockEntry::new(template, &data.context, content));
+        data.outputs.insert(content);
+        data.lock.record_file(&path, FileStatus::Created);
+        return data.sink.write_file(&data.repo, &path, content);
     }
 
-    debug!("Writing {} bytes to {}", content.len(), full_path.display());
-    std::fs::write(&full_path, content).map_err(|e| ActionIoError::new_write_file(full_path, e))?;
+    let existing = std::fs::read_to_string(&full_path)
+        .map_err(|e| ActionIoError::new_read_file(&full_path, e))?;
+    let merged = merge_regions(&existing, content)
+        .map_err(|e| ActionIoError::new_managed_regions(&full_path, e))?;
+    // The file with the managed regions replaced is what boiler generated, so
+    // that the rest of it is not considered a local change; the local changes
+    // merged with the generated content are still recorded as such
+    let (merged, generated) = match merged {
+        Some(merged) => (merged.clone(), merged),
+        None => (
+            merge_local_changes(data, &path, &existing, content),
+            content.to_owned(),
+        ),
+    };
+    data.lock
+        .record(&path, LockEntry::new(template, &data.context, &generated));
+    data.outputs.insert(&generated);
+
+    let status = if merged == existing {
+        FileStatus::Unchanged
+    } else {
+        FileStatus::Updated
+    };
+    data.lock.record_file(&path, status);
+    data.sink.write_file(&data.repo, &path, &merged)
+}
 
-    Ok(())
+/// Merges the changes made to the file at `path` since it was last generated
+/// into `content`, unless [`ActionData::keep_local_changes`] is unset.
+fn merge_local_changes(data: &ActionData, path: &Path, existing: &str, content: &str) -> String {
+    if !data.keep_local_changes {
+        return content.to_owned();
+    }
+
+    let previous = data.lock.previous().get(path);
+    match merge_user_edits(previous, &data.outputs, existing, content) {
+        MergeResult::Clean(merged) => merged,
+        MergeResult::Conflicted(merged) => {
+            warn!("Could not merge the local changes to {}", path.display());
+            data.lock.record_conflict(path);
+            merged
+        }
+    }
 }
@@ -1,111 +1,516 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 
-use serde::Deserialize;
+use globset::{GlobBuilder, GlobMatcher};
+use log::warn;
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
 use thiserror::Error;
 
 use crate::actions::{Action, ActionName};
-use crate::data::Value;
+use crate::context_schema::{self, SchemaError, CONTEXT_SCHEMA};
+use crate::data::{is_repo_relative, Repo, Value};
+use crate::detectors::DetectorName;
 use crate::function_meta::FunctionEnabled;
 
-#[derive(Debug, Deserialize)]
+/// Configuration for multiple repositories, keyed by `owner/name`.
+///
+/// The keys can contain glob patterns, such as `m4tx/*` or `*/*-sys`. When
+/// several keys match a repository, their configs are merged from the least
+/// to the most specific one. Keys with the same specificity, such as `m4tx/*`
+/// and `*/*-sys`, are merged in the order they are written in, so the last one
+/// takes priority.
+#[derive(Debug, Clone, Default)]
 pub struct ReposConfig {
-    repos: HashMap<String, RepoConfig>,
+    entries: Vec<ReposConfigEntry>,
+}
+
+#[derive(Debug, Clone)]
+struct ReposConfigEntry {
+    source: String,
+    pattern: String,
+    matcher: GlobMatcher,
+    config: RepoConfig,
+}
+
+impl ReposConfigEntry {
+    fn new(source: String, pattern: String, config: RepoConfig) -> Result<Self, RepoConfigError> {
+        let matcher = GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|source| RepoConfigError::InvalidPattern {
+                pattern: pattern.clone(),
+                source,
+            })?
+            .compile_matcher();
+
+        let entry = Self {
+            source,
+            pattern,
+            matcher,
+            config,
+        };
+        entry.config.validate(&entry.location())?;
+
+        Ok(entry)
+    }
+
+    /// Returns the description of where the entry comes from.
+    fn location(&self) -> String {
+        format!("{} ({})", self.source, self.pattern)
+    }
+
+    fn is_exact(&self) -> bool {
+        !self.pattern.contains(['*', '?', '[', '{'])
+    }
+
+    /// Returns a sort key; greater keys are more specific.
+    fn specificity(&self) -> (bool, usize) {
+        let literal_chars = self
+            .pattern
+            .chars()
+            .filter(|c| !matches!(c, '*' | '?' | '[' | ']' | '{' | '}'))
+            .count();
+
+        (self.is_exact(), literal_chars)
+    }
 }
 
 impl ReposConfig {
+    /// Name of the source of the config built into boiler.
+    pub const BUILT_IN_SOURCE: &'static str = "built-in overrides";
+
     #[must_use]
     pub fn from_yaml_string(yaml_string: &str) -> Self {
-        let repos = serde_yaml::from_str(yaml_string).unwrap();
-        Self { repos }
+        Self::try_from_yaml_string(yaml_string, Self::BUILT_IN_SOURCE).unwrap()
     }
 
+    fn try_from_yaml_string(
+        yaml_string: &str,
+        source: &str,
+    ) -> Result<Self, ReposConfigParseError> {
+        let OrderedEntries(repos) = serde_yaml::from_str(yaml_string)?;
+        let entries = repos
+            .into_iter()
+            .map(|(pattern, config)| ReposConfigEntry::new(source.to_owned(), pattern, config))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { entries })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, RepoConfigError> {
+        let yaml_string =
+            std::fs::read_to_string(path).map_err(|source| RepoConfigError::ReadFile {
+                path: path.to_owned(),
+                source,
+            })?;
+
+        Self::try_from_yaml_string(&yaml_string, &path.display().to_string()).map_err(|error| {
+            match error {
+                ReposConfigParseError::Yaml(source) => RepoConfigError::Parse {
+                    path: path.to_owned(),
+                    source,
+                },
+                ReposConfigParseError::Config(error) => error,
+            }
+        })
+    }
+
+    /// Returns the config built into boiler.
     #[must_use]
-    pub fn get(&self, key: &str) -> Option<&RepoConfig> {
-        self.repos.get(key)
+    pub fn built_in() -> Self {
+        Self::from_yaml_string(include_str!("overrides.yml"))
     }
+
+    /// Loads the config built into boiler, followed by the user-level config
+    /// file (if it exists) and the config file at `config_path` (if given).
+    ///
+    /// The later sources take priority over the earlier ones.
+    pub fn load(config_path: Option<&Path>) -> Result<Self, RepoConfigError> {
+        let mut repos_config = Self::built_in();
+
+        if let Some(user_config_path) = user_config_path() {
+            if user_config_path.exists() {
+                repos_config.extend(Self::from_file(&user_config_path)?);
+            }
+        }
+        if let Some(config_path) = config_path {
+            repos_config.extend(Self::from_file(config_path)?);
+        }
+
+        Ok(repos_config)
+    }
+
+    /// Adds the entries from `other`, giving them priority over the existing
+    /// entries with the same specificity.
+    pub fn extend(&mut self, other: ReposConfig) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Returns the merged config of all the entries matching `key`, or `None`
+    /// if there are no such entries.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<RepoConfig> {
+        let matching = self.matching(key);
+        if matching.is_empty() {
+            return None;
+        }
+
+        let mut repo_config = RepoConfig::default();
+        for (_source, config) in matching {
+            repo_config = repo_config.override_with(config);
+        }
+
+        Some(repo_config)
+    }
+
+    /// Returns all the entries matching `key`, from the least to the most
+    /// specific one, along with the description of where they come from.
+    #[must_use]
+    pub fn matching(&self, key: &str) -> Vec<(String, &RepoConfig)> {
+        let mut matching: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.matcher.is_match(key))
+            .collect();
+        matching.sort_by_key(|entry| entry.specificity());
+
+        matching
+            .into_iter()
+            .map(|entry| (entry.location(), &entry.config))
+            .collect()
+    }
+}
+
+/// The entries of a config file, in the order they are written in, which
+/// decides the priority of the entries with the same specificity.
+struct OrderedEntries(Vec<(String, RepoConfig)>);
+
+impl<'de> Deserialize<'de> for OrderedEntries {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OrderedEntriesVisitor;
+
+        impl<'de> Visitor<'de> for OrderedEntriesVisitor {
+            type Value = OrderedEntries;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a map of repositories to their configs")
+            }
+
+            fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+                Ok(OrderedEntries(Vec::new()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(OrderedEntries(entries))
+            }
+        }
+
+        deserializer.deserialize_map(OrderedEntriesVisitor)
+    }
+}
+
+/// Returns the path of the user-level config file, i.e.
+/// `$XDG_CONFIG_HOME/boiler/config.yml`, falling back to
+/// `~/.config/boiler/config.yml`.
+#[must_use]
+pub fn user_config_path() -> Option<PathBuf> {
+    Some(user_config_dir()?.join("config.yml"))
 }
 
+/// Returns the directory with the user-level boiler files, i.e.
+/// `$XDG_CONFIG_HOME/boiler`, falling back to `~/.config/boiler`.
+#[must_use]
+pub fn user_config_dir() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home::home_dir().map(|home| home.join(".config")))?;
+
+    Some(config_dir.join("boiler"))
+}
+
+#[derive(Debug, Error)]
+enum ReposConfigParseError {
+    #[error(transparent)]
+    Yaml(#[from] serde_yaml::Error),
+    #[error(transparent)]
+    Config(#[from] RepoConfigError),
+}
+
+/// Name of the per-repository config file, read from the repository root.
+pub const REPO_CONFIG_FILENAME: &str = ".boiler.yml";
+
 #[derive(Debug, Error)]
 pub enum RepoConfigError {
     #[error("Invalid action name: {0}")]
     InvalidActionName(ActionName),
+    #[error("Invalid detector name: {0}")]
+    InvalidDetectorName(DetectorName),
+    #[error("Could not read config file: {path}")]
+    ReadFile {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Could not parse config file: {path}")]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: serde_yaml::Error,
+    },
+    #[error("Custom action has the same name as a built-in action: {0}")]
+    DuplicateActionName(ActionName),
+    #[error("Custom action {name} in {location} uses a path outside of the repository: {path}")]
+    InvalidCustomActionPath {
+        location: String,
+        name: ActionName,
+        path: PathBuf,
+    },
+    #[error("Invalid context in {location}")]
+    InvalidContext {
+        location: String,
+        #[source]
+        source: SchemaError,
+    },
+    #[error("Invalid repository pattern: {pattern}")]
+    InvalidPattern {
+        pattern: String,
+        #[source]
+        source: globset::Error,
+    },
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct RepoConfig {
+    /// If set, only these actions are run.
+    #[serde(default)]
+    actions_included: Option<BTreeSet<ActionName>>,
+    /// Actions to run even though they are disabled by default.
+    #[serde(default)]
+    actions_enabled: BTreeSet<ActionName>,
     #[serde(default)]
     actions_excluded: BTreeSet<ActionName>,
+    /// If set, only these detectors are run.
+    #[serde(default)]
+    detectors_included: Option<BTreeSet<DetectorName>>,
+    /// Detectors to run even though they are disabled by default.
+    #[serde(default)]
+    detectors_enabled: BTreeSet<DetectorName>,
+    #[serde(default)]
+    detectors_excluded: BTreeSet<DetectorName>,
     #[serde(default = "Value::empty_object")]
     context: Value,
+    #[serde(default)]
+    merge: MergeConfig,
+    #[serde(default)]
+    templates_dir: Option<PathBuf>,
+    #[serde(default)]
+    custom_actions: Vec<CustomActionConfig>,
 }
 
 impl Default for RepoConfig {
     fn default() -> Self {
         Self {
+            actions_included: None,
+            actions_enabled: BTreeSet::new(),
             actions_excluded: BTreeSet::new(),
+            detectors_included: None,
+            detectors_enabled: BTreeSet::new(),
+            detectors_excluded: BTreeSet::new(),
             context: Value::empty_object(),
+            merge: MergeConfig::default(),
+            templates_dir: None,
+            custom_actions: Vec::new(),
         }
     }
 }
 
 impl RepoConfig {
+    /// Loads the [`REPO_CONFIG_FILENAME`] file from the repository root, if it
+    /// exists.
+    pub fn from_repo(repo: &Repo) -> Result<Option<Self>, RepoConfigError> {
+        let path = repo.path().join(REPO_CONFIG_FILENAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let yaml_string =
+            std::fs::read_to_string(&path).map_err(|source| RepoConfigError::ReadFile {
+                path: path.clone(),
+                source,
+            })?;
+        let config: Self =
+            serde_yaml::from_str(&yaml_string).map_err(|source| RepoConfigError::Parse {
+                path: path.clone(),
+                source,
+            })?;
+        config.validate(&path.display().to_string())?;
+
+        Ok(Some(config))
+    }
+
+    /// Checks the config loaded from `location`, as described in
+    /// [`Self::validate_context`] and [`Self::validate_custom_actions`].
+    fn validate(&self, location: &str) -> Result<(), RepoConfigError> {
+        self.validate_context(location)?;
+        self.validate_custom_actions(location)
+    }
+
+    /// Checks that the templates and the outputs of the custom actions are
+    /// paths inside the repository, so that a config cannot read or write
+    /// arbitrary files.
+    fn validate_custom_actions(&self, location: &str) -> Result<(), RepoConfigError> {
+        for action in &self.custom_actions {
+            for path in [Path::new(&action.template), &action.output] {
+                if !is_repo_relative(path) {
+                    return Err(RepoConfigError::InvalidCustomActionPath {
+                        location: location.to_owned(),
+                        name: action.name.clone(),
+                        path: path.to_owned(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the context overrides against
+    /// [`CONTEXT_SCHEMA`](crate::context_schema::CONTEXT_SCHEMA).
+    ///
+    /// Values of wrong types are errors; unknown keys are only reported as
+    /// warnings, as they can be used by custom templates.
+    fn validate_context(&self, location: &str) -> Result<(), RepoConfigError> {
+        for error in context_schema::validate(&self.context) {
+            match error {
+                SchemaError::UnknownKey(key) => warn!(
+                    "Unknown context key {key} in {location}; it is only available to custom \
+                     templates"
+                ),
+                SchemaError::TypeMismatch { .. } => {
+                    return Err(RepoConfigError::InvalidContext {
+                        location: location.to_owned(),
+                        source: error,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Only runs the given actions, replacing the `actions_included` setting.
+    #[must_use]
+    pub fn with_actions_included<T: IntoIterator<Item = ActionName>>(mut self, names: T) -> Self {
+        self.actions_included = Some(names.into_iter().collect());
+        self
+    }
+
+    /// Adds the given actions to the `actions_excluded` setting.
+    #[must_use]
+    pub fn with_actions_excluded<T: IntoIterator<Item = ActionName>>(mut self, names: T) -> Self {
+        self.actions_excluded.extend(names);
+        self
+    }
+
+    /// Adds the given detectors to the `detectors_excluded` setting.
+    #[must_use]
+    pub fn with_detectors_excluded<T: IntoIterator<Item = DetectorName>>(
+        mut self,
+        names: T,
+    ) -> Self {
+        self.detectors_excluded.extend(names);
+        self
+    }
+
+    #[must_use]
+    pub fn actions_included(&self) -> Option<&BTreeSet<ActionName>> {
+        self.actions_included.as_ref()
+    }
+
+    #[must_use]
+    pub fn actions_enabled(&self) -> &BTreeSet<ActionName> {
+        &self.actions_enabled
+    }
+
     #[must_use]
     pub fn actions_excluded(&self) -> &BTreeSet<ActionName> {
         &self.actions_excluded
     }
 
+    #[must_use]
+    pub fn detectors_included(&self) -> Option<&BTreeSet<DetectorName>> {
+        self.detectors_included.as_ref()
+    }
+
+    #[must_use]
+    pub fn detectors_enabled(&self) -> &BTreeSet<DetectorName> {
+        &self.detectors_enabled
+    }
+
+    #[must_use]
+    pub fn detectors_excluded(&self) -> &BTreeSet<DetectorName> {
+        &self.detectors_excluded
+    }
+
     pub fn filter_actions<'a>(
         &self,
         actions: &[&'a dyn Action],
     ) -> Result<Vec<&'a dyn Action>, RepoConfigError> {
-        for action_name in &self.actions_excluded {
-            if !actions
-                .iter()
-                .any(|action| action.name() == action_name.name())
-            {
-                return Err(RepoConfigError::InvalidActionName(action_name.clone()));
-            }
-        }
+        let actions_enabled =
+            self.create_actions_enabled(&crate::actions::create_actions_enabled_for(actions))?;
 
         Ok(actions
             .iter()
-            .filter(|action| {
-                !self
-                    .actions_excluded
-                    .contains(&ActionName::new(action.name().to_string()))
-            })
+            .filter(|action| actions_enabled.is_enabled(action.name()))
             .copied()
             .collect())
     }
 
+    /// Enables only the `actions_included` (if set) and the
+    /// `actions_enabled`, and then disables the `actions_excluded`; fails if
+    /// any of them is not in `actions_enabled`.
     pub fn create_actions_enabled(
         &self,
         actions_enabled: &FunctionEnabled,
     ) -> Result<FunctionEnabled, RepoConfigError> {
-        for action_excluded in &self.actions_excluded {
-            if !actions_enabled
-                .function_names()
-                .any(|action_name| action_name == action_excluded.name())
-            {
-                return Err(RepoConfigError::InvalidActionName(action_excluded.clone()));
-            }
-        }
-
-        let mut actions_enabled = actions_enabled.clone();
-        for action_excluded in &self.actions_excluded {
-            actions_enabled.set_enabled(action_excluded.name().to_owned(), false);
-        }
+        select_functions(
+            actions_enabled,
+            self.actions_included.as_ref(),
+            &self.actions_enabled,
+            &self.actions_excluded,
+            ActionName::name,
+        )
+        .map_err(|name| RepoConfigError::InvalidActionName(name.clone()))
+    }
 
-        Ok(actions_enabled)
+    /// Enables only the `detectors_included` (if set) and the
+    /// `detectors_enabled`, and then disables the `detectors_excluded`; fails
+    /// if any of them is not in `detectors_enabled`.
+    pub fn create_detectors_enabled(
+        &self,
+        detectors_enabled: &FunctionEnabled,
+    ) -> Result<FunctionEnabled, RepoConfigError> {
+        select_functions(
+            detectors_enabled,
+            self.detectors_included.as_ref(),
+            &self.detectors_enabled,
+            &self.detectors_excluded,
+            DetectorName::name,
+        )
+        .map_err(|name| RepoConfigError::InvalidDetectorName(name.clone()))
     }
 
+    /// Merges the configs; the `*_enabled` and `*_excluded` settings are
+    /// combined, while the `*_included` ones and the rest of the settings from
+    /// `other` take priority.
     #[must_use]
     pub fn override_with(&mut self, other: &RepoConfig) -> Self {
-        let actions_excluded = self
-            .actions_excluded
-            .union(&other.actions_excluded)
-            .cloned()
-            .collect();
         let context = {
             let mut context = self.context.clone();
             context.override_with(&other.context);
@@ -113,8 +518,41 @@ impl RepoConfig {
         };
 
         Self {
-            actions_excluded,
+            actions_included: other
+                .actions_included
+                .clone()
+                .or_else(|| self.actions_included.clone()),
+            actions_enabled: self
+                .actions_enabled
+                .union(&other.actions_enabled)
+                .cloned()
+                .collect(),
+            actions_excluded: self
+                .actions_excluded
+                .union(&other.actions_excluded)
+                .cloned()
+                .collect(),
+            detectors_included: other
+                .detectors_included
+                .clone()
+                .or_else(|| self.detectors_included.clone()),
+            detectors_enabled: self
+                .detectors_enabled
+                .union(&other.detectors_enabled)
+                .cloned()
+                .collect(),
+            detectors_excluded: self
+                .detectors_excluded
+                .union(&other.detectors_excluded)
+                .cloned()
+                .collect(),
             context,
+            merge: self.merge.override_with(&other.merge),
+            templates_dir: other
+                .templates_dir
+                .clone()
+                .or_else(|| self.templates_dir.clone()),
+            custom_actions: merge_custom_actions(&self.custom_actions, &other.custom_actions),
         }
     }
 
@@ -122,34 +560,599 @@ impl RepoConfig {
     pub fn context(&self) -> &Value {
         &self.context
     }
+
+    #[must_use]
+    pub fn merge(&self) -> &MergeConfig {
+        &self.merge
+    }
+
+    /// Returns the directory with the templates overriding the built-in ones;
+    /// relative paths are relative to the repository root.
+    #[must_use]
+    pub fn templates_dir(&self) -> Option<&Path> {
+        self.templates_dir.as_deref()
+    }
+
+    #[must_use]
+    pub fn custom_actions(&self) -> &[CustomActionConfig] {
+        &self.custom_actions
+    }
 }
 
+/// Applies the allow-list `included` (if set), `opted_in` and then `excluded`
+/// to `enabled`; returns the first name that is not in `enabled` as an error.
+fn select_functions<'a, T>(
+    enabled: &FunctionEnabled,
+    included: Option<&'a BTreeSet<T>>,
+    opted_in: &'a BTreeSet<T>,
+    excluded: &'a BTreeSet<T>,
+    name: fn(&T) -> &str,
+) -> Result<FunctionEnabled, &'a T> {
+    for function in included
+        .into_iter()
+        .flatten()
+        .chain(opted_in)
+        .chain(excluded)
+    {
+        if !enabled
+            .function_names()
+            .any(|function_name| function_name == name(function))
+        {
+            return Err(function);
+        }
+    }
+
+    let mut enabled = enabled.clone();
+    if let Some(included) = included {
+        let included: BTreeSet<_> = included.iter().map(name).collect();
+        for (function_name, is_enabled) in enabled.iter_mut() {
+            *is_enabled = included.contains(function_name.as_str());
+        }
+    }
+    for function in opted_in {
+        enabled.set_enabled(name(function).to_owned(), true);
+    }
+    for function in excluded {
+        enabled.set_enabled(name(function).to_owned(), false);
+    }
+
+    Ok(enabled)
+}
+
+/// Returns the actions from `actions`, replaced with the ones from `other`
+/// that have the same name, followed by the rest of `other`.
+#[must_use]
+pub fn merge_custom_actions(
+    actions: &[CustomActionConfig],
+    other: &[CustomActionConfig],
+) -> Vec<CustomActionConfig> {
+    let mut merged = actions.to_vec();
+    for action in other {
+        match merged
+            .iter_mut()
+            .find(|existing| existing.name == action.name)
+        {
+            Some(existing) => *existing = action.clone(),
+            None => merged.push(action.clone()),
+        }
+    }
+
+    merged
+}
+
+/// An action declared in the config that renders a single template.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CustomActionConfig {
+    name: ActionName,
+    #[serde(default)]
+    description: Option<String>,
+    template: String,
+    output: PathBuf,
+    #[serde(default)]
+    condition: Option<String>,
+}
+
+impl CustomActionConfig {
+    #[must_use]
+    pub fn name(&self) -> &ActionName {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Returns the name of the template; either one known to the template
+    /// engine, or a path relative to the repository root.
+    #[must_use]
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// Returns the path of the generated file, relative to the repository
+    /// root.
+    #[must_use]
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+
+    /// Returns the Tera expression that has to be true for the action to run,
+    /// e.g. `'go' in boiler.langs`.
+    #[must_use]
+    pub fn condition(&self) -> Option<&str> {
+        self.condition.as_deref()
+    }
+}
+
+/// What to do when two detectors produce different values for the same key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergePolicy {
+    /// Abort the run with an error.
+    #[default]
+    Error,
+    /// Keep the value of the detector that comes first.
+    FirstWins,
+    /// Keep the value of the detector with the highest priority, as set in
+    /// [`MergeConfig`]; ties are resolved like with
+    /// [`MergePolicy::FirstWins`].
+    Priority,
+}
+
+/// Configures how the results of the detectors are merged together.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MergeConfig {
+    /// The policy used for the keys not listed in `policies`.
+    #[serde(default)]
+    default_policy: Option<MergePolicy>,
+    /// The policies for specific keys; nested keys are separated with dots.
+    #[serde(default)]
+    policies: BTreeMap<String, MergePolicy>,
+    /// The priorities of the detectors; the default priority is 0.
+    #[serde(default)]
+    detector_priorities: BTreeMap<String, i64>,
+}
+
+impl MergeConfig {
+    /// Returns the policy for the value at `path`, falling back to the
+    /// policies of its parents and then to the default policy.
+    #[must_use]
+    pub fn policy(&self, path: &str) -> MergePolicy {
+        let mut path = path;
+        loop {
+            if let Some(policy) = self.policies.get(path) {
+                return *policy;
+            }
+            match path.rfind('.') {
+                Some(index) => path = &path[..index],
+                None => break,
+            }
+        }
+
+        self.default_policy.unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn detector_priority(&self, detector_name: &str) -> i64 {
+        self.detector_priorities
+            .get(detector_name)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn override_with(&self, other: &MergeConfig) -> Self {
+        let mut policies = self.policies.clone();
+        policies.extend(other.policies.clone());
+        let mut detector_priorities = self.detector_priorities.clone();
+        detector_priorities.extend(other.detector_priorities.clone());
+
+        Self {
+            default_policy: other.default_policy.or(self.default_policy),
+            policies,
+            detector_priorities,
+        }
+    }
+}
+
+/// The stage of building the context of a repository.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextStage {
+    /// Only the values found by the detectors.
+    Detected,
+    /// The detected values on top of [`default_context_data`].
+    Defaults,
+    /// The context with all the config overrides applied, as seen by the
+    /// actions.
+    Final,
+}
+
+/// Returns the default values of the keys in
+/// [`CONTEXT_SCHEMA`](crate::context_schema::CONTEXT_SCHEMA), used when no
+/// detector finds them.
 pub fn default_context_data() -> Value {
     let mut data = Value::empty_object();
 
-    data.insert(crate::context_keys::CRATE_PUBLISHED, true);
-    data.insert(crate::context_keys::COVERAGE_ENABLED, true);
-    data.insert(crate::context_keys::LICENSE, "LicenseRef-proprietary");
-    data.insert(crate::context_keys::LANGS, []);
-    data.insert(crate::context_keys::FRAMEWORKS, []);
-    data.insert(
-        crate::context_keys::GH_ACTIONS_RUST_VERSIONS,
-        [Value::new_string("stable"), Value::new_string("nightly")],
-    );
-    data.insert(
-        crate::context_keys::GH_ACTIONS_RUST_OS,
-        [
-            Value::new_string("ubuntu-latest"),
-            Value::new_string("macos-latest"),
-            Value::new_string("windows-latest"),
-        ],
-    );
-    data.insert(crate::context_keys::GH_ACTIONS_RUST_FEATURES, []);
-    data.insert(crate::context_keys::TRUNK_CONFIGS, []);
-    data.insert(
-        crate::context_keys::REPO_DEFAULT_BRANCH,
-        Value::new_string("master"),
-    );
+    for schema in CONTEXT_SCHEMA.iter() {
+        if let Some(default) = &schema.default {
+            data.insert(schema.key, default.clone());
+        }
+    }
 
     data
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::context::{
+        MergePolicy, RepoConfig, RepoConfigError, ReposConfig, REPO_CONFIG_FILENAME,
+    };
+    use crate::data::Value;
+    use crate::function_meta::FunctionEnabled;
+    use crate::test_utils::TempRepo;
+
+    #[test]
+    fn test_from_repo_missing() {
+        let repo = TempRepo::new();
+
+        assert!(RepoConfig::from_repo(&repo.repo()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_from_repo() {
+        let repo = TempRepo::new();
+        repo.write_str(
+            REPO_CONFIG_FILENAME,
+            "actions_excluded: [RustCi]\ncontext:\n  coverage_enabled: false\n",
+        );
+
+        let config = RepoConfig::from_repo(&repo.repo()).unwrap().unwrap();
+
+        assert_eq!(config.actions_excluded().len(), 1);
+        assert_eq!(
+            config.context(),
+            &Value::new_object([("coverage_enabled".to_owned(), Value::new_bool(false))])
+        );
+    }
+
+    #[test]
+    fn test_from_repo_invalid() {
+        let repo = TempRepo::new();
+        repo.write_str(REPO_CONFIG_FILENAME, "actions_excluded: 5\n");
+
+        assert!(RepoConfig::from_repo(&repo.repo()).is_err());
+    }
+
+    #[test]
+    fn test_from_repo_invalid_context() {
+        let repo = TempRepo::new();
+        repo.write_str(
+            REPO_CONFIG_FILENAME,
+            "context:\n  gh_actions_rust_os: ubuntu-latest\n",
+        );
+
+        let error = RepoConfig::from_repo(&repo.repo()).unwrap_err();
+
+        assert!(matches!(error, RepoConfigError::InvalidContext { .. }));
+        assert!(error.to_string().contains(REPO_CONFIG_FILENAME));
+    }
+
+    #[test]
+    fn test_from_repo_unknown_context_key() {
+        let repo = TempRepo::new();
+        repo.write_str(REPO_CONFIG_FILENAME, "context:\n  coverage_enable: false\n");
+
+        assert!(RepoConfig::from_repo(&repo.repo()).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_repos_config_invalid_context() {
+        let repo = TempRepo::new();
+        repo.write_str(
+            "config.yml",
+            "m4tx/*:\n  context:\n    crate_published: 'no'\n",
+        );
+
+        let error = ReposConfig::from_file(&repo.path().join("config.yml")).unwrap_err();
+
+        assert!(error.to_string().contains("config.yml (m4tx/*)"));
+    }
+
+    #[test]
+    fn test_repos_config_exact() {
+        let repos_config = ReposConfig::from_yaml_string(
+            "m4tx/boiler:\n  context:\n    coverage_enabled: false\n",
+        );
+
+        assert!(repos_config.get("m4tx/other").is_none());
+        assert_eq!(
+            repos_config.get("m4tx/boiler").unwrap().context(),
+            &Value::new_object([("coverage_enabled".to_owned(), Value::new_bool(false))])
+        );
+    }
+
+    #[test]
+    fn test_repos_config_wildcard_specificity() {
+        let repos_config = ReposConfig::from_yaml_string(
+            r"
+m4tx/ryg-rans-sys:
+  context:
+    name: exact
+'*/*':
+  context:
+    name: any
+    crate_published: false
+'*/*-sys':
+  context:
+    name: sys
+    coverage_enabled: false
+m4tx/*:
+  context:
+    name: owner
+",
+        );
+
+        assert_eq!(
+            repos_config.get("m4tx/ryg-rans-sys").unwrap().context(),
+            &Value::new_object([
+                ("name".to_owned(), Value::new_string("exact")),
+                ("crate_published".to_owned(), Value::new_bool(false)),
+                ("coverage_enabled".to_owned(), Value::new_bool(false)),
+            ])
+        );
+        assert_eq!(
+            repos_config.get("m4tx/boiler").unwrap().context()["name"],
+            Value::new_string("owner")
+        );
+        assert_eq!(
+            repos_config.get("riichi/chombot").unwrap().context()["name"],
+            Value::new_string("any")
+        );
+        assert!(repos_config.get("not-a-repo").is_none());
+    }
+
+    #[test]
+    fn test_repos_config_same_specificity() {
+        let repos_config = ReposConfig::from_yaml_string(
+            r"
+m4tx/*:
+  context:
+    name: owner
+'*/*-sys':
+  context:
+    name: sys
+",
+        );
+        assert_eq!(
+            repos_config.get("m4tx/ryg-rans-sys").unwrap().context()["name"],
+            Value::new_string("sys")
+        );
+
+        let repos_config = ReposConfig::from_yaml_string(
+            r"
+'*/*-sys':
+  context:
+    name: sys
+m4tx/*:
+  context:
+    name: owner
+",
+        );
+        assert_eq!(
+            repos_config.get("m4tx/ryg-rans-sys").unwrap().context()["name"],
+            Value::new_string("owner")
+        );
+    }
+
+    #[test]
+    fn test_repos_config_extend_priority() {
+        let mut repos_config =
+            ReposConfig::from_yaml_string("m4tx/boiler:\n  context:\n    name: builtin\n");
+        repos_config.extend(ReposConfig::from_yaml_string(
+            "m4tx/boiler:\n  context:\n    name: user\n",
+        ));
+
+        assert_eq!(
+            repos_config.get("m4tx/boiler").unwrap().context()["name"],
+            Value::new_string("user")
+        );
+    }
+
+    #[test]
+    fn test_merge_config() {
+        let repos_config = ReposConfig::from_yaml_string(
+            r"
+'*/*':
+  merge:
+    default_policy: first-wins
+    policies:
+      full_name: error
+m4tx/boiler:
+  merge:
+    policies:
+      full_name: priority
+      a: error
+    detector_priorities:
+      License: 10
+",
+        );
+
+        let config = repos_config.get("m4tx/boiler").unwrap();
+        let merge = config.merge();
+        assert_eq!(merge.policy("full_name"), MergePolicy::Priority);
+        assert_eq!(merge.policy("license"), MergePolicy::FirstWins);
+        assert_eq!(merge.policy("a.b"), MergePolicy::Error);
+        assert_eq!(merge.detector_priority("License"), 10);
+        assert_eq!(merge.detector_priority("Rust"), 0);
+
+        let config = repos_config.get("m4tx/other").unwrap();
+        assert_eq!(config.merge().policy("full_name"), MergePolicy::Error);
+        assert_eq!(
+            RepoConfig::default().merge().policy("x"),
+            MergePolicy::Error
+        );
+    }
+
+    #[test]
+    fn test_custom_actions() {
+        let repos_config = ReposConfig::from_yaml_string(
+            r#"
+'*/*':
+  custom_actions:
+    - name: GoCi
+      template: go.yml.j2
+      output: .github/workflows/go.yml
+      condition: "'go' in boiler.langs"
+    - name: Editorconfig
+      template: editorconfig.j2
+      output: .editorconfig
+m4tx/boiler:
+  custom_actions:
+    - name: GoCi
+      template: go-custom.yml.j2
+      output: .github/workflows/go.yml
+"#,
+        );
+
+        let config = repos_config.get("m4tx/boiler").unwrap();
+        let actions = config.custom_actions();
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0].name().name(), "GoCi");
+        assert_eq!(actions[0].template(), "go-custom.yml.j2");
+        assert_eq!(actions[0].condition(), None);
+        assert_eq!(actions[1].output(), Path::new(".editorconfig"));
+
+        let config = repos_config.get("m4tx/other").unwrap();
+        assert_eq!(
+            config.custom_actions()[0].condition(),
+            Some("'go' in boiler.langs")
+        );
+    }
+
+    #[test]
+    fn test_custom_action_output_outside_repo() {
+        let repo = TempRepo::new();
+        repo.write_str(
+            REPO_CONFIG_FILENAME,
+            "custom_actions:\n  - name: Bashrc\n    template: bashrc.j2\n    output: ../../.bashrc\n",
+        );
+
+        let error = RepoConfig::from_repo(&repo.repo()).unwrap_err();
+
+        assert!(matches!(
+            error,
+            RepoConfigError::InvalidCustomActionPath { path, .. } if path == Path::new("../../.bashrc")
+        ));
+    }
+
+    #[test]
+    fn test_custom_action_absolute_paths() {
+        let repo = TempRepo::new();
+        repo.write_str(
+            "config.yml",
+            "'*/*':\n  custom_actions:\n    - name: Passwd\n      template: /etc/passwd\n      output: passwd\n",
+        );
+        let error = ReposConfig::from_file(&repo.path().join("config.yml")).unwrap_err();
+        assert!(error.to_string().contains("config.yml (*/*)"));
+
+        repo.write_str(
+            "config.yml",
+            "'*/*':\n  custom_actions:\n    - name: Passwd\n      template: passwd.j2\n      output: /tmp/passwd\n",
+        );
+        assert!(ReposConfig::from_file(&repo.path().join("config.yml")).is_err());
+    }
+
+    fn function_enabled(names: &[&str]) -> FunctionEnabled {
+        let mut enabled = FunctionEnabled::new();
+        for name in names {
+            enabled.set_enabled((*name).to_owned(), true);
+        }
+        enabled
+    }
+
+    fn enabled_names(enabled: &FunctionEnabled) -> Vec<&str> {
+        enabled
+            .iter()
+            .filter(|(_name, is_enabled)| **is_enabled)
+            .map(|(name, _is_enabled)| name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_create_actions_enabled() {
+        let config: RepoConfig = serde_yaml::from_str(
+            "actions_included: [License, Readme]\nactions_excluded: [Readme]\n",
+        )
+        .unwrap();
+
+        let enabled = config
+            .create_actions_enabled(&function_enabled(&["License", "Readme", "RustCi"]))
+            .unwrap();
+
+        assert_eq!(enabled_names(&enabled), ["License"]);
+    }
+
+    #[test]
+    fn test_create_actions_enabled_invalid() {
+        let config: RepoConfig = serde_yaml::from_str("actions_included: [Licence]\n").unwrap();
+
+        let error = config
+            .create_actions_enabled(&function_enabled(&["License"]))
+            .unwrap_err();
+
+        assert!(matches!(error, RepoConfigError::InvalidActionName(_)));
+    }
+
+    #[test]
+    fn test_create_detectors_enabled() {
+        let config: RepoConfig = serde_yaml::from_str("detectors_excluded: [Git]\n").unwrap();
+        let enabled = config
+            .create_detectors_enabled(&function_enabled(&["Git", "Rust"]))
+            .unwrap();
+        assert_eq!(enabled_names(&enabled), ["Rust"]);
+
+        let config: RepoConfig = serde_yaml::from_str("detectors_included: [Gti]\n").unwrap();
+        let error = config
+            .create_detectors_enabled(&function_enabled(&["Git", "Rust"]))
+            .unwrap_err();
+        assert!(matches!(error, RepoConfigError::InvalidDetectorName(_)));
+    }
+
+    #[test]
+    fn test_create_actions_enabled_opt_in() {
+        let config: RepoConfig = serde_yaml::from_str("actions_enabled: [GoCi]\n").unwrap();
+        let mut actions_enabled = function_enabled(&["License"]);
+        actions_enabled.set_enabled("GoCi".to_owned(), false);
+        actions_enabled.set_enabled("RustCi".to_owned(), false);
+
+        let enabled = config.create_actions_enabled(&actions_enabled).unwrap();
+
+        assert_eq!(enabled_names(&enabled), ["GoCi", "License"]);
+    }
+
+    #[test]
+    fn test_override_included() {
+        let mut config: RepoConfig =
+            serde_yaml::from_str("actions_included: [License]\nactions_excluded: [RustCi]\n")
+                .unwrap();
+        let other: RepoConfig =
+            serde_yaml::from_str("actions_included: [Readme]\nactions_excluded: [Readme]\n")
+                .unwrap();
+
+        let merged = config.override_with(&other);
+
+        assert_eq!(merged.actions_included().unwrap().len(), 1);
+        assert_eq!(merged.actions_excluded().len(), 2);
+        assert_eq!(
+            enabled_names(
+                &merged
+                    .create_actions_enabled(&function_enabled(&["License", "Readme", "RustCi"]))
+                    .unwrap()
+            ),
+            Vec::<&str>::new()
+        );
+    }
+}
@@ -1,5 +1,6 @@
 pub const CONTEXT_ROOT: &str = "boiler";
 
+pub const APT_DEPENDENCIES: &str = "apt_dependencies";
 pub const COVERAGE_ENABLED: &str = "coverage_enabled";
 pub const CRATE_NAME: &str = "crate_name";
 pub const CRATE_PUBLISHED: &str = "crate_published";
@@ -22,3 +23,14 @@ pub const REPO_OWNER: &str = "repo_owner";
 pub const RUST_MSRV: &str = "rust_msrv";
 pub const TRUNK_CONFIGS: &str = "trunk_configs";
 pub const VCS: &str = "vcs";
+
+/// Keys whose values are arrays of unique items; merging them keeps the order
+/// of the items, but drops the duplicates.
+pub const SET_KEYS: [&str; 6] = [
+    DOCKERFILES,
+    FRAMEWORKS,
+    LANGS,
+    PYTHON_PACKAGE_MANAGERS,
+    TRUNK_CONFIGS,
+    VCS,
+];
@@ -0,0 +1,310 @@
+//! The keys known to boiler in the context, along with their types,
+//! descriptions and default values.
+
+use std::fmt::{Display, Formatter};
+
+use once_cell::sync::Lazy;
+use thiserror::Error;
+
+use crate::context_keys;
+use crate::data::Value;
+
+/// The type of the value of a context key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Bool,
+    Integer,
+    String,
+    StringArray,
+}
+
+impl ValueType {
+    #[must_use]
+    pub fn matches(self, value: &Value) -> bool {
+        match self {
+            Self::Bool => value.as_bool().is_some(),
+            Self::Integer => value
+                .as_number()
+                .is_some_and(|number| number.as_integer().is_some()),
+            Self::String => value.as_string().is_some(),
+            Self::StringArray => value
+                .as_array()
+                .is_some_and(|array| array.iter().all(|item| item.as_string().is_some())),
+        }
+    }
+}
+
+impl Display for ValueType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool => write!(f, "a boolean"),
+            Self::Integer => write!(f, "an integer"),
+            Self::String => write!(f, "a string"),
+            Self::StringArray => write!(f, "an array of strings"),
+        }
+    }
+}
+
+/// The description of a context key.
+#[derive(Debug, Clone)]
+pub struct KeySchema {
+    pub key: &'static str,
+    pub value_type: ValueType,
+    pub description: &'static str,
+    /// The value used when no detector finds one.
+    pub default: Option<Value>,
+}
+
+impl KeySchema {
+    fn new(key: &'static str, value_type: ValueType, description: &'static str) -> Self {
+        Self {
+            key,
+            value_type,
+            description,
+            default: None,
+        }
+    }
+
+    fn with_default<T: Into<Value>>(mut self, default: T) -> Self {
+        self.default = Some(default.into());
+        self
+    }
+}
+
+fn strings(values: &[&str]) -> Value {
+    Value::new_array(
+        values
+            .iter()
+            .map(|value| Value::new_string(*value))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// All the keys known to boiler, sorted by name.
+pub static CONTEXT_SCHEMA: Lazy<Vec<KeySchema>> = Lazy::new(|| {
+    use ValueType::{Bool, Integer, String, StringArray};
+
+    vec![
+        KeySchema::new(
+            context_keys::APT_DEPENDENCIES,
+            String,
+            "APT packages installed in the Rust CI workflow, separated with spaces",
+        ),
+        KeySchema::new(
+            context_keys::COVERAGE_ENABLED,
+            Bool,
+            "Whether the CI workflow collects the code coverage",
+        )
+        .with_default(true),
+        KeySchema::new(context_keys::CRATE_NAME, String, "Name of the Rust crate"),
+        KeySchema::new(
+            context_keys::CRATE_PUBLISHED,
+            Bool,
+            "Whether the Rust crate is published on crates.io",
+        )
+        .with_default(true),
+        KeySchema::new(
+            context_keys::DOCKERFILES,
+            StringArray,
+            "Dockerfiles in the repository root",
+        ),
+        KeySchema::new(
+            context_keys::FIRST_ACTIVITY_YEAR,
+            Integer,
+            "Year of the first commit",
+        ),
+        KeySchema::new(
+            context_keys::FRAMEWORKS,
+            StringArray,
+            "Frameworks used in the project",
+        )
+        .with_default(strings(&[])),
+        KeySchema::new(
+            context_keys::FULL_NAME,
+            String,
+            "Full name of the author, used e.g. in the license",
+        ),
+        KeySchema::new(
+            context_keys::GH_ACTIONS_RUST_FEATURES,
+            StringArray,
+            "Comma-separated sets of crate features tested in the Rust CI workflow",
+        )
+        .with_default(strings(&[])),
+        KeySchema::new(
+            context_keys::GH_ACTIONS_RUST_OS,
+            StringArray,
+            "Operating systems the Rust CI workflow runs on",
+        )
+        .with_default(strings(&[
+            "ubuntu-latest",
+            "macos-latest",
+            "windows-latest",
+        ])),
+        KeySchema::new(
+            context_keys::GH_ACTIONS_RUST_VERSIONS,
+            StringArray,
+            "Rust toolchains the Rust CI workflow runs on",
+        )
+        .with_default(strings(&["stable", "nightly"])),
+        KeySchema::new(
+            context_keys::GIT_HAS_SUBMODULES,
+            Bool,
+            "Whether the git repository has submodules",
+        ),
+        KeySchema::new(
+            context_keys::LANGS,
+            StringArray,
+            "Languages used in the project",
+        )
+        .with_default(strings(&[])),
+        KeySchema::new(
+            context_keys::LAST_ACTIVITY_YEAR,
+            Integer,
+            "Year of the last commit",
+        ),
+        KeySchema::new(
+            context_keys::LICENSE,
+            String,
+            "SPDX identifier of the license",
+        )
+        .with_default("LicenseRef-proprietary"),
+        KeySchema::new(context_keys::NAME, String, "Name of the project"),
+        KeySchema::new(
+            context_keys::PYTHON_PACKAGE_MANAGERS,
+            StringArray,
+            "Python package managers used in the project",
+        ),
+        KeySchema::new(
+            context_keys::REPO_DEFAULT_BRANCH,
+            String,
+            "Default branch of the repository",
+        )
+        .with_default("master"),
+        KeySchema::new(
+            context_keys::REPO_NAME,
+            String,
+            "Name of the repository on GitHub",
+        ),
+        KeySchema::new(
+            context_keys::REPO_OWNER,
+            String,
+            "Owner of the repository on GitHub",
+        ),
+        KeySchema::new(
+            context_keys::RUST_MSRV,
+            String,
+            "Minimum supported Rust version",
+        ),
+        KeySchema::new(
+            context_keys::TRUNK_CONFIGS,
+            StringArray,
+            "Trunk configuration files built in the Rust CI workflow",
+        )
+        .with_default(strings(&[])),
+        KeySchema::new(
+            context_keys::VCS,
+            StringArray,
+            "Version control systems used in the repository",
+        ),
+    ]
+});
+
+/// Returns the schema of `key`, if it is known to boiler.
+#[must_use]
+pub fn key_schema(key: &str) -> Option<&'static KeySchema> {
+    CONTEXT_SCHEMA.iter().find(|schema| schema.key == key)
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum SchemaError {
+    #[error("unknown context key: {0}")]
+    UnknownKey(String),
+    #[error("invalid value of {key}: expected {expected}, found {found}")]
+    TypeMismatch {
+        key: String,
+        expected: ValueType,
+        found: &'static str,
+    },
+}
+
+/// Returns the kind of `value`, as used in the error messages.
+fn describe(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Checks the keys of `context` against [`CONTEXT_SCHEMA`], returning the
+/// unknown keys and the values of wrong types.
+#[must_use]
+pub fn validate(context: &Value) -> Vec<SchemaError> {
+    let Some(context) = context.as_object() else {
+        return Vec::new();
+    };
+
+    context
+        .iter()
+        .filter_map(|(key, value)| match key_schema(key) {
+            None => Some(SchemaError::UnknownKey(key.clone())),
+            Some(schema) if !schema.value_type.matches(value) => Some(SchemaError::TypeMismatch {
+                key: key.clone(),
+                expected: schema.value_type,
+                found: describe(value),
+            }),
+            Some(_) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::default_context_data;
+    use crate::context_schema::{validate, SchemaError, ValueType, CONTEXT_SCHEMA};
+    use crate::data::Value;
+
+    #[test]
+    fn test_schema_sorted() {
+        let keys: Vec<_> = CONTEXT_SCHEMA.iter().map(|schema| schema.key).collect();
+        let mut sorted_keys = keys.clone();
+        sorted_keys.sort_unstable();
+
+        assert_eq!(keys, sorted_keys);
+    }
+
+    #[test]
+    fn test_defaults_valid() {
+        assert_eq!(validate(&default_context_data()), Vec::new());
+    }
+
+    #[test]
+    fn test_validate() {
+        let context = Value::new_object([
+            ("coverage_enable".to_owned(), Value::new_bool(false)),
+            (
+                "gh_actions_rust_os".to_owned(),
+                Value::new_string("ubuntu-latest"),
+            ),
+            (
+                "langs".to_owned(),
+                Value::new_array([Value::new_string("rust")]),
+            ),
+        ]);
+
+        assert_eq!(
+            validate(&context),
+            [
+                SchemaError::UnknownKey("coverage_enable".to_owned()),
+                SchemaError::TypeMismatch {
+                    key: "gh_actions_rust_os".to_owned(),
+                    expected: ValueType::StringArray,
+                    found: "a string",
+                },
+            ]
+        );
+    }
+}
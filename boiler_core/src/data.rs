@@ -1,26 +1,59 @@
 use std::collections::BTreeMap;
 use std::ops::{Index, IndexMut};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 
+use once_cell::sync::OnceCell;
 use serde::de::Visitor;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
 
+use crate::file_index::{FileIndex, FileIndexError};
+
 #[derive(Clone, Debug)]
 pub struct Repo {
     path: PathBuf,
+    files: Arc<OnceCell<FileIndex>>,
 }
 
 impl Repo {
     #[must_use]
     pub fn new<T: Into<PathBuf>>(path: T) -> Self {
-        Self { path: path.into() }
+        Self {
+            path: path.into(),
+            files: Arc::new(OnceCell::new()),
+        }
     }
 
     #[must_use]
     pub fn path(&self) -> &Path {
         &self.path
     }
+
+    /// Returns the index of the files in the repository, building it on the
+    /// first call; the index is shared by all the clones of this `Repo`.
+    pub fn files(&self) -> Result<&FileIndex, FileIndexError> {
+        self.files.get_or_try_init(|| FileIndex::build(&self.path))
+    }
+}
+
+/// Returns whether `path` is relative and does not go up the directory tree,
+/// i.e. whether, relative to the repository root, it stays inside the
+/// repository.
+#[must_use]
+pub fn is_repo_relative(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// Returns the path of `key` in the object at `path`, with the keys separated
+/// with dots.
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{path}.{key}")
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -98,6 +131,25 @@ pub enum ValueUnionError {
     },
 }
 
+/// Decides how two arrays are merged in [`Value::union_with`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArrayMergeMode {
+    /// Append all the items of the other array.
+    #[default]
+    Append,
+    /// Append only the items that are not present yet, treating the arrays
+    /// as ordered sets.
+    Set,
+}
+
+/// Decides what to do with two incompatible values in [`Value::union_with`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictResolution {
+    KeepExisting,
+    UseNew,
+    Fail,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Value {
     Null,
@@ -184,6 +236,14 @@ impl Value {
         }
     }
 
+    /// Returns the value at the given path, with the object keys separated
+    /// with dots (e.g. `a.b`).
+    #[must_use]
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        path.split('.')
+            .try_fold(self, |value, key| value.as_object()?.get(key))
+    }
+
     pub fn insert<T: Into<String>, U: Into<Value>>(&mut self, key: T, value: U) {
         match self {
             Self::Object(map) => {
@@ -194,53 +254,123 @@ impl Value {
     }
 
     pub fn union(&mut self, other: &Self) -> Result<(), ValueUnionError> {
-        match (self, other) {
+        self.union_with(
+            other,
+            &|_path| ArrayMergeMode::Append,
+            &mut |_path, _a, _b| ConflictResolution::Fail,
+        )
+    }
+
+    /// Merges `other` into `self` like [`Value::union`], but calls
+    /// `array_merge_mode` to decide how to merge each pair of arrays, and
+    /// `resolve` for each pair of incompatible values to decide which one to
+    /// keep.
+    ///
+    /// The first argument passed to the functions is the path of the value,
+    /// with the object keys separated with dots.
+    pub fn union_with<F>(
+        &mut self,
+        other: &Self,
+        array_merge_mode: &dyn Fn(&str) -> ArrayMergeMode,
+        resolve: &mut F,
+    ) -> Result<(), ValueUnionError>
+    where
+        F: FnMut(&str, &Value, &Value) -> ConflictResolution,
+    {
+        self.union_at("", other, array_merge_mode, resolve)
+    }
+
+    fn union_at<F>(
+        &mut self,
+        path: &str,
+        other: &Self,
+        array_merge_mode: &dyn Fn(&str) -> ArrayMergeMode,
+        resolve: &mut F,
+    ) -> Result<(), ValueUnionError>
+    where
+        F: FnMut(&str, &Value, &Value) -> ConflictResolution,
+    {
+        let error = match (&mut *self, other) {
             (Self::Object(a), Self::Object(b)) => {
                 for (key, value) in b {
+                    let key_path = join_path(path, key);
                     if let Some(self_value) = a.get_mut(key) {
-                        self_value.union(value).map_err(|e| {
-                            ValueUnionError::IncompatibleObject {
+                        self_value
+                            .union_at(&key_path, value, array_merge_mode, resolve)
+                            .map_err(|e| ValueUnionError::IncompatibleObject {
                                 key: key.clone(),
                                 error: Box::new(e),
-                            }
-                        })?;
+                            })?;
                     } else {
-                        a.insert(key.clone(), value.clone());
+                        let mut value = value.clone();
+                        value.dedup_sets(&key_path, array_merge_mode);
+                        a.insert(key.clone(), value);
                     }
                 }
+                None
             }
             (Self::Array(a), Self::Array(b)) => {
-                for value in b {
-                    a.push(value.clone());
+                a.extend(b.iter().cloned());
+                if array_merge_mode(path) == ArrayMergeMode::Set {
+                    self.dedup();
                 }
+                None
             }
             (Self::String(a), Self::String(b)) => {
-                if a != b {
-                    return Err(ValueUnionError::IncompatibleStringValues(
-                        a.clone(),
-                        b.clone(),
-                    ));
-                }
+                (a != b).then(|| ValueUnionError::IncompatibleStringValues(a.clone(), b.clone()))
             }
             (Self::Number(a), Self::Number(b)) => {
-                if a != b {
-                    return Err(ValueUnionError::IncompatibleNumberValues(*a, *b));
-                }
+                (a != b).then_some(ValueUnionError::IncompatibleNumberValues(*a, *b))
             }
             (Self::Bool(a), Self::Bool(b)) => {
-                if a != b {
-                    return Err(ValueUnionError::IncompatibleBoolValues(*a, *b));
-                }
+                (a != b).then_some(ValueUnionError::IncompatibleBoolValues(*a, *b))
             }
-            (Self::Null, Self::Null) => {}
-            (a, b) => {
-                return Err(ValueUnionError::IncompatibleTypes(a.clone(), b.clone()));
+            (Self::Null, Self::Null) => None,
+            (a, b) => Some(ValueUnionError::IncompatibleTypes(a.clone(), b.clone())),
+        };
+
+        if let Some(error) = error {
+            match resolve(path, self, other) {
+                ConflictResolution::KeepExisting => {}
+                ConflictResolution::UseNew => {
+                    *self = other.clone();
+                    self.dedup_sets(path, array_merge_mode);
+                }
+                ConflictResolution::Fail => return Err(error),
             }
         }
 
         Ok(())
     }
 
+    /// Removes the duplicated items of an array, keeping the first occurrence
+    /// of each of them. Does nothing for other types of values.
+    pub fn dedup(&mut self) {
+        if let Self::Array(array) = self {
+            let mut unique = Vec::with_capacity(array.len());
+            for value in array.drain(..) {
+                if !unique.contains(&value) {
+                    unique.push(value);
+                }
+            }
+            *array = unique;
+        }
+    }
+
+    /// Removes the duplicated items of the arrays merged as sets according to
+    /// `array_merge_mode`, including the nested ones.
+    fn dedup_sets(&mut self, path: &str, array_merge_mode: &dyn Fn(&str) -> ArrayMergeMode) {
+        match self {
+            Self::Object(map) => {
+                for (key, value) in map {
+                    value.dedup_sets(&join_path(path, key), array_merge_mode);
+                }
+            }
+            Self::Array(_) if array_merge_mode(path) == ArrayMergeMode::Set => self.dedup(),
+            _ => {}
+        }
+    }
+
     pub fn override_with(&mut self, other: &Self) {
         match (self, other) {
             (Self::Object(self_map), Self::Object(other_map)) => {
@@ -259,6 +389,10 @@ impl Value {
     pub fn as_yaml(&self) -> String {
         serde_yaml::to_string(self).expect("could not serialize value to yaml")
     }
+
+    pub fn as_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("could not serialize value to json")
+    }
 }
 
 impl Default for Value {
@@ -493,7 +627,7 @@ impl<'de> Deserialize<'de> for Value {
 
 #[cfg(test)]
 mod tests {
-    use crate::data::{Number, Value};
+    use crate::data::{ArrayMergeMode, ConflictResolution, Number, Value};
 
     #[test]
     fn test_serialize_value_yaml() {
@@ -553,6 +687,41 @@ f:
         assert_eq!(value, value_deserialized);
     }
 
+    #[test]
+    fn test_serialize_value_json() {
+        let value = Value::new_object([
+            ("a".to_owned(), Value::new_number(1)),
+            (
+                "b".to_owned(),
+                Value::new_array(vec![Value::new_bool(true), Value::new_string("hello")]),
+            ),
+        ]);
+
+        assert_eq!(
+            value.as_json(),
+            r#"{
+  "a": 1,
+  "b": [
+    true,
+    "hello"
+  ]
+}"#
+        );
+    }
+
+    #[test]
+    fn test_value_get_path() {
+        let value = Value::new_object([(
+            "a".to_owned(),
+            Value::new_object([("b".to_owned(), Value::new_bool(true))]),
+        )]);
+
+        assert_eq!(value.get_path("a.b"), Some(&Value::new_bool(true)));
+        assert!(value.get_path("a").unwrap().as_object().is_some());
+        assert_eq!(value.get_path("a.c"), None);
+        assert_eq!(value.get_path("a.b.c"), None);
+    }
+
     #[test]
     fn test_value_null_union() {
         let mut val = Value::new_null();
@@ -580,6 +749,54 @@ f:
         assert!(val.union(&Value::new_string("other")).is_err());
     }
 
+    #[test]
+    fn test_value_union_with_resolution() {
+        let mut val = Value::new_object([
+            ("a".to_owned(), Value::new_string("a")),
+            ("b".to_owned(), Value::new_string("b")),
+        ]);
+        let mut conflicts = Vec::new();
+
+        val.union_with(
+            &Value::new_object([
+                ("a".to_owned(), Value::new_string("X")),
+                ("b".to_owned(), Value::new_string("Y")),
+            ]),
+            &|_path| ArrayMergeMode::Append,
+            &mut |path, _a, _b| {
+                conflicts.push(path.to_owned());
+                if path == "a" {
+                    ConflictResolution::KeepExisting
+                } else {
+                    ConflictResolution::UseNew
+                }
+            },
+        )
+        .unwrap();
+
+        assert_eq!(conflicts, vec!["a".to_owned(), "b".to_owned()]);
+        assert_eq!(
+            val,
+            Value::new_object([
+                ("a".to_owned(), Value::new_string("a")),
+                ("b".to_owned(), Value::new_string("Y")),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_value_union_with_fail() {
+        let mut val = Value::new_object([("a".to_owned(), Value::new_number(1))]);
+
+        let result = val.union_with(
+            &Value::new_object([("a".to_owned(), Value::new_string("a"))]),
+            &|_path| ArrayMergeMode::Append,
+            &mut |_path, _a, _b| ConflictResolution::Fail,
+        );
+
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_value_array_union() {
         let mut val = Value::new_array(vec![Value::new_string("a")]);
@@ -591,6 +808,81 @@ f:
         );
     }
 
+    #[test]
+    fn test_value_array_union_set() {
+        let mut val = Value::new_object([(
+            "langs".to_owned(),
+            Value::new_array(vec![Value::new_string("a"), Value::new_string("b")]),
+        )]);
+        val.union_with(
+            &Value::new_object([(
+                "langs".to_owned(),
+                Value::new_array(vec![Value::new_string("b"), Value::new_string("c")]),
+            )]),
+            &|path| {
+                if path == "langs" {
+                    ArrayMergeMode::Set
+                } else {
+                    ArrayMergeMode::Append
+                }
+            },
+            &mut |_path, _a, _b| ConflictResolution::Fail,
+        )
+        .unwrap();
+
+        assert_eq!(
+            val["langs"],
+            Value::new_array(vec![
+                Value::new_string("a"),
+                Value::new_string("b"),
+                Value::new_string("c")
+            ])
+        );
+    }
+
+    #[test]
+    fn test_value_array_union_set_new_key() {
+        let mut val = Value::empty_object();
+        val.union_with(
+            &Value::new_object([(
+                "repo".to_owned(),
+                Value::new_object([(
+                    "langs".to_owned(),
+                    Value::new_array(vec![Value::new_string("a"), Value::new_string("a")]),
+                )]),
+            )]),
+            &|path| {
+                if path == "repo.langs" {
+                    ArrayMergeMode::Set
+                } else {
+                    ArrayMergeMode::Append
+                }
+            },
+            &mut |_path, _a, _b| ConflictResolution::Fail,
+        )
+        .unwrap();
+
+        assert_eq!(
+            val["repo"]["langs"],
+            Value::new_array(vec![Value::new_string("a")])
+        );
+    }
+
+    #[test]
+    fn test_value_dedup() {
+        let mut val = Value::new_array(vec![
+            Value::new_string("b"),
+            Value::new_string("a"),
+            Value::new_string("b"),
+        ]);
+        val.dedup();
+
+        assert_eq!(
+            val,
+            Value::new_array(vec![Value::new_string("b"), Value::new_string("a")])
+        );
+    }
+
     #[test]
     fn test_value_object_union() {
         let mut a = Value::new_object([
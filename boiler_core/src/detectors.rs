@@ -1,22 +1,16 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
 use anyhow::Context;
-use chrono::Utc;
-use docker::DockerDetector;
-use git::GitDetector;
-use license::LicenseDetector;
-use log::debug;
+use log::{debug, warn};
 use once_cell::sync::Lazy;
-use python::PythonDetector;
-use readme::ReadmeDetector;
-use rust::RustDetector;
-
-use crate::context::default_context_data;
-use crate::data::{Repo, Value};
-use crate::detectors::javascript::JavascriptDetector;
-use crate::detectors::json::JsonDetector;
-use crate::detectors::shell_script::ShellScriptDetector;
-use crate::detectors::toml::TomlDetector;
-use crate::detectors::yaml::YamlDetector;
+use serde::{Deserialize, Serialize};
+
+use crate::context::{default_context_data, MergeConfig, MergePolicy};
+use crate::context_keys;
+use crate::data::{ArrayMergeMode, ConflictResolution, Repo, Value};
 use crate::function_meta::{FunctionEnabled, FunctionMeta};
+use crate::provenance::{Provenance, Source};
 
 mod docker;
 mod git;
@@ -30,72 +24,303 @@ mod shell_script;
 mod toml;
 mod yaml;
 
-pub(crate) type DetectorResult = anyhow::Result<Value>;
+pub type DetectorResult = anyhow::Result<Value>;
 
+/// Finds information about a repository.
+///
+/// Detectors looking for files should use the index returned by
+/// [`Repo::files`], shared by all the detectors, rather than walking the
+/// repository themselves.
 pub trait Detector: FunctionMeta + Send + Sync {
     fn detect(&self, repo: &Repo) -> DetectorResult;
+
+    /// Returns whether the detector needs the context found by the other
+    /// detectors; such detectors are run after all the others, with
+    /// [`Detector::detect_with_context`].
+    fn needs_context(&self) -> bool {
+        false
+    }
+
+    /// Finds information about a repository, given the merged results of the
+    /// detectors that do not need the context.
+    fn detect_with_context(&self, repo: &Repo, _context: &Value) -> DetectorResult {
+        self.detect(repo)
+    }
 }
 
-pub static DETECTORS: Lazy<[Box<dyn Detector>; 11]> = Lazy::new(|| {
-    [
-        Box::new(DockerDetector),
-        Box::new(GitDetector::new(Utc)),
-        Box::new(JavascriptDetector),
-        Box::new(JsonDetector),
-        Box::new(LicenseDetector),
-        Box::new(PythonDetector),
-        Box::new(ReadmeDetector),
-        Box::new(RustDetector),
-        Box::new(ShellScriptDetector),
-        Box::new(TomlDetector),
-        Box::new(YamlDetector),
-    ]
+/// A detector registered with the [`boiler_macros::detector`] attribute.
+#[derive(Debug)]
+pub struct DetectorRegistration {
+    create: fn() -> Arc<dyn Detector>,
+}
+
+impl DetectorRegistration {
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn new(create: fn() -> Arc<dyn Detector>) -> Self {
+        Self { create }
+    }
+}
+
+inventory::collect!(DetectorRegistration);
+
+/// All the registered detectors, including the ones defined in the other
+/// crates linked in, sorted by name.
+pub static DETECTORS: Lazy<Vec<Arc<dyn Detector>>> = Lazy::new(|| {
+    let mut detectors: Vec<_> = inventory::iter::<DetectorRegistration>
+        .into_iter()
+        .map(|registration| (registration.create)())
+        .collect();
+    detectors.sort_by(|a, b| a.name().cmp(b.name()));
+    detectors
 });
 
 pub fn detect(repo: &Repo, detectors_enabled: &FunctionEnabled) -> DetectorResult {
+    Ok(detect_with_provenance(repo, detectors_enabled, &MergeConfig::default())?.0)
+}
+
+/// Runs the detectors like [`detect`], merging their results according to
+/// `merge_config` and keeping track of which detector set each value.
+pub fn detect_with_provenance(
+    repo: &Repo,
+    detectors_enabled: &FunctionEnabled,
+    merge_config: &MergeConfig,
+) -> anyhow::Result<(Value, Provenance)> {
+    let results = run_detectors(repo, &DETECTORS, detectors_enabled)?;
+    merge_detector_results(&results, merge_config)
+}
+
+/// Runs the enabled `detectors` that do not need the context concurrently,
+/// returning their names along with their (not yet merged) results, in the
+/// order of `detectors`.
+pub fn run_detectors<'a>(
+    repo: &Repo,
+    detectors: &'a [Arc<dyn Detector>],
+    detectors_enabled: &FunctionEnabled,
+) -> anyhow::Result<Vec<(&'a str, Value)>> {
+    // Build the index before the detectors need it, so that they do not wait for
+    // each other
+    repo.files()
+        .context("Could not index the files in the repository")?;
+
+    run_concurrently(
+        detectors
+            .iter()
+            .filter(|detector| !detector.needs_context())
+            .filter(|detector| detectors_enabled.is_enabled(detector.name())),
+        |detector| detector.detect(repo),
+    )
+}
+
+/// Runs the enabled `detectors` that need the context like [`run_detectors`],
+/// passing them `context`.
+pub fn run_detectors_with_context<'a>(
+    repo: &Repo,
+    detectors: &'a [Arc<dyn Detector>],
+    detectors_enabled: &FunctionEnabled,
+    context: &Value,
+) -> anyhow::Result<Vec<(&'a str, Value)>> {
+    run_concurrently(
+        detectors
+            .iter()
+            .filter(|detector| detector.needs_context())
+            .filter(|detector| detectors_enabled.is_enabled(detector.name())),
+        |detector| detector.detect_with_context(repo, context),
+    )
+}
+
+fn run_concurrently<'a, I, F>(detectors: I, detect: F) -> anyhow::Result<Vec<(&'a str, Value)>>
+where
+    I: Iterator<Item = &'a Arc<dyn Detector>>,
+    F: Fn(&dyn Detector) -> DetectorResult + Sync,
+{
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = detectors
+            .map(|detector| {
+                let detect = &detect;
+                scope.spawn(move || {
+                    debug!("Running detector: {}", detector.name());
+                    let detector_result = detect(detector.as_ref())
+                        .with_context(|| format!("Failed to run detector: {}", detector.name()))?;
+                    Ok((detector.name(), detector_result))
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    })
+}
+
+/// Merges the results of [`run_detectors`] with [`Value::union_with`],
+/// resolving the conflicts according to `merge_config`.
+pub fn merge_detector_results(
+    results: &[(&str, Value)],
+    merge_config: &MergeConfig,
+) -> anyhow::Result<(Value, Provenance)> {
     let mut data = Value::empty_object();
+    let mut provenance = Provenance::new();
+
+    for (detector_name, detector_result) in results {
+        let source = Source::Detector((*detector_name).to_owned());
+        let mut resolved = Vec::new();
+
+        data.union_with(
+            detector_result,
+            &array_merge_mode,
+            &mut |path, existing, new| {
+                let existing_sources: Vec<_> = provenance
+                    .get(path)
+                    .flat_map(|(_path, sources)| sources.iter().cloned())
+                    .collect();
+                let resolution =
+                    resolve_conflict(merge_config, path, &existing_sources, detector_name);
 
-    for detector in DETECTORS.iter() {
-        if detectors_enabled.is_enabled(detector.name()) {
-            let detector_result = detector
-                .detect(repo)
-                .with_context(|| format!("Failed to run detector: {}", detector.name()))?;
-            data.union(&detector_result).with_context(|| {
-                format!("Failed to combine detector result: {}", detector.name())
-            })?;
+                match resolution {
+                    ConflictResolution::KeepExisting => {
+                        warn!(
+                            "Detector {detector_name} found a different value for `{path}`: {}; \
+                             keeping {}",
+                            new.as_json(),
+                            existing.as_json()
+                        );
+                        resolved.push((path.to_owned(), existing_sources));
+                    }
+                    ConflictResolution::UseNew => {
+                        warn!(
+                            "Detector {detector_name} found a different value for `{path}`: {}; \
+                             replacing {}",
+                            new.as_json(),
+                            existing.as_json()
+                        );
+                        resolved.push((path.to_owned(), vec![source.clone()]));
+                    }
+                    ConflictResolution::Fail => {}
+                }
+
+                resolution
+            },
+        )
+        .with_context(|| format!("Failed to combine detector result: {detector_name}"))?;
+
+        provenance.record_union(detector_result, &source);
+        for (path, sources) in resolved {
+            provenance.replace(&path, sources);
         }
     }
 
-    Ok(data)
+    Ok((data, provenance))
+}
+
+fn array_merge_mode(path: &str) -> ArrayMergeMode {
+    if context_keys::SET_KEYS.contains(&path) {
+        ArrayMergeMode::Set
+    } else {
+        ArrayMergeMode::Append
+    }
+}
+
+fn resolve_conflict(
+    merge_config: &MergeConfig,
+    path: &str,
+    existing_sources: &[Source],
+    detector_name: &str,
+) -> ConflictResolution {
+    match merge_config.policy(path) {
+        MergePolicy::Error => ConflictResolution::Fail,
+        MergePolicy::FirstWins => ConflictResolution::KeepExisting,
+        MergePolicy::Priority => {
+            let existing_priority = existing_sources
+                .iter()
+                .filter_map(|source| match source {
+                    Source::Detector(name) => Some(merge_config.detector_priority(name)),
+                    _ => None,
+                })
+                .max()
+                .unwrap_or(i64::MIN);
+
+            if merge_config.detector_priority(detector_name) > existing_priority {
+                ConflictResolution::UseNew
+            } else {
+                ConflictResolution::KeepExisting
+            }
+        }
+    }
 }
 
 pub fn detect_with_defaults(repo: &Repo, detectors_enabled: &FunctionEnabled) -> DetectorResult {
-    let data = detect(repo, detectors_enabled)?;
+    let (data, provenance) =
+        detect_with_provenance(repo, detectors_enabled, &MergeConfig::default())?;
+    Ok(apply_defaults(data, provenance).0)
+}
 
+/// Puts the detected context on top of [`default_context_data`].
+#[must_use]
+pub fn apply_defaults(data: Value, provenance: Provenance) -> (Value, Provenance) {
     let mut data_with_defaults = default_context_data();
+    let mut provenance_with_defaults = Provenance::new();
+    provenance_with_defaults.record_union(&data_with_defaults, &Source::Default);
+
     data_with_defaults.override_with(&data);
+    provenance_with_defaults.override_with(&provenance);
 
-    Ok(data_with_defaults)
+    (data_with_defaults, provenance_with_defaults)
 }
 
 pub fn create_detectors_enabled() -> FunctionEnabled {
+    create_detectors_enabled_for(&DETECTORS)
+}
+
+pub fn create_detectors_enabled_for(detectors: &[Arc<dyn Detector>]) -> FunctionEnabled {
     let mut detectors_enabled = FunctionEnabled::new();
 
-    for detector in DETECTORS.iter() {
-        debug!("Running detector: {}", detector.name());
+    for detector in detectors {
         detectors_enabled.set_enabled(detector.name().to_owned(), detector.default_enabled());
     }
 
     detectors_enabled
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+#[repr(transparent)]
+pub struct DetectorName(String);
+
+impl DetectorName {
+    pub fn new(name: String) -> Self {
+        Self(name)
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for DetectorName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::context::{MergeConfig, ReposConfig};
     use crate::context_keys;
     use crate::data::Value;
     use crate::detectors::json::JsonDetector;
-    use crate::detectors::{create_detectors_enabled, detect, detect_with_defaults};
+    use crate::detectors::{
+        apply_defaults, create_detectors_enabled, detect, detect_with_defaults,
+        detect_with_provenance, merge_detector_results, DETECTORS,
+    };
     use crate::function_meta::FunctionMeta;
+    use crate::provenance::Source;
     use crate::test_utils::TempRepo;
 
     #[test]
@@ -145,4 +370,161 @@ mod tests {
             ),])
         );
     }
+
+    #[test]
+    fn test_detect_provenance() {
+        let temp_repo = TempRepo::new();
+        temp_repo.write_str("test.json", "{}");
+
+        let mut detectors_enabled = create_detectors_enabled();
+        for (_fn_name, enabled) in detectors_enabled.iter_mut() {
+            *enabled = false;
+        }
+        detectors_enabled.set_enabled(JsonDetector.name().to_owned(), true);
+
+        let (data, provenance) = detect_with_provenance(
+            &temp_repo.repo(),
+            &detectors_enabled,
+            &MergeConfig::default(),
+        )
+        .unwrap();
+        let (_data, provenance) = apply_defaults(data, provenance);
+        assert_eq!(
+            provenance.get(context_keys::LANGS).collect::<Vec<_>>(),
+            vec![(
+                context_keys::LANGS,
+                [Source::Detector("Json".to_owned())].as_slice()
+            )]
+        );
+        assert_eq!(
+            provenance.get(context_keys::LICENSE).collect::<Vec<_>>(),
+            vec![(context_keys::LICENSE, [Source::Default].as_slice())]
+        );
+    }
+
+    fn conflicting_results() -> Vec<(&'static str, Value)> {
+        vec![
+            (
+                "Rust",
+                Value::new_object([(
+                    context_keys::FULL_NAME.to_owned(),
+                    Value::new_string("John Doe"),
+                )]),
+            ),
+            (
+                "License",
+                Value::new_object([(
+                    context_keys::FULL_NAME.to_owned(),
+                    Value::new_string("Jane Doe"),
+                )]),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_merge_langs_deduplicated() {
+        let langs = Value::new_object([(
+            context_keys::LANGS.to_owned(),
+            Value::new_array(vec![Value::new_string("rust")]),
+        )]);
+        let results = vec![
+            ("Rust", langs.clone()),
+            ("Other", langs),
+            (
+                "Toml",
+                Value::new_object([(
+                    context_keys::LANGS.to_owned(),
+                    Value::new_array(vec![Value::new_string("toml"), Value::new_string("rust")]),
+                )]),
+            ),
+        ];
+
+        let (data, _provenance) =
+            merge_detector_results(&results, &MergeConfig::default()).unwrap();
+
+        assert_eq!(
+            data[context_keys::LANGS],
+            Value::new_array(vec![Value::new_string("rust"), Value::new_string("toml")])
+        );
+    }
+
+    fn merge_config(yaml: &str) -> MergeConfig {
+        ReposConfig::from_yaml_string(yaml)
+            .get("m4tx/boiler")
+            .unwrap()
+            .merge()
+            .clone()
+    }
+
+    #[test]
+    fn test_merge_conflict_error() {
+        let result = merge_detector_results(&conflicting_results(), &MergeConfig::default());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_merge_conflict_first_wins() {
+        let merge_config = merge_config("m4tx/boiler:\n  merge:\n    default_policy: first-wins\n");
+
+        let (data, provenance) =
+            merge_detector_results(&conflicting_results(), &merge_config).unwrap();
+
+        assert_eq!(data[context_keys::FULL_NAME], Value::new_string("John Doe"));
+        assert_eq!(
+            provenance.get(context_keys::FULL_NAME).collect::<Vec<_>>(),
+            vec![(
+                context_keys::FULL_NAME,
+                [Source::Detector("Rust".to_owned())].as_slice()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_merge_conflict_priority() {
+        let merge_config = merge_config(
+            r"
+m4tx/boiler:
+  merge:
+    policies:
+      full_name: priority
+    detector_priorities:
+      License: 10
+",
+        );
+
+        let (data, provenance) =
+            merge_detector_results(&conflicting_results(), &merge_config).unwrap();
+
+        assert_eq!(data[context_keys::FULL_NAME], Value::new_string("Jane Doe"));
+        assert_eq!(
+            provenance.get(context_keys::FULL_NAME).collect::<Vec<_>>(),
+            vec![(
+                context_keys::FULL_NAME,
+                [Source::Detector("License".to_owned())].as_slice()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_detectors_registered() {
+        let names: Vec<_> = DETECTORS.iter().map(|detector| detector.name()).collect();
+
+        assert_eq!(
+            names,
+            [
+                "Docker",
+                "Git",
+                "Javascript",
+                "Json",
+                "License",
+                "Python",
+                "Readme",
+                "Rust",
+                "ShellScript",
+                "Toml",
+                "Yaml",
+            ]
+        );
+    }
 }
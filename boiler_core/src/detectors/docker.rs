@@ -11,6 +11,7 @@ pub enum DockerDetectorError {
 }
 
 /// Detects the existence of Dockerfiles.
+#[boiler_macros::detector]
 #[derive(Debug, boiler_macros::FunctionMeta)]
 pub struct DockerDetector;
 
@@ -1,5 +1,5 @@
 use anyhow::Context;
-use boiler_macros::FunctionMeta;
+use boiler_macros::{detector, FunctionMeta};
 use chrono::{DateTime, Datelike, Utc};
 use gix::bstr::ByteSlice;
 use gix::revision::walk::Info;
@@ -14,6 +14,7 @@ use crate::time::Clock;
 
 /// Detects if the project is using git as the VCS and detects basic metadata,
 /// such as repository owner/name and the activity period.
+#[detector]
 #[derive(Debug, FunctionMeta)]
 pub struct GitDetector<C = Utc> {
     clock: C,
@@ -54,6 +55,12 @@ impl<C: Clock + Send + Sync> Detector for GitDetector<C> {
     }
 }
 
+impl Default for GitDetector {
+    fn default() -> Self {
+        Self::new(Utc)
+    }
+}
+
 impl<C: Clock + Send + Sync> GitDetector<C> {
     #[must_use]
     pub const fn new(clock: C) -> Self {
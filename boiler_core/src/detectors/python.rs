@@ -1,4 +1,4 @@
-use boiler_macros::FunctionMeta;
+use boiler_macros::{detector, FunctionMeta};
 
 use crate::context_keys;
 use crate::data::{Repo, Value};
@@ -6,6 +6,7 @@ use crate::detectors::{Detector, DetectorResult};
 
 /// Detects if the project contains Python files and checks which package
 /// manager the project is using.
+#[detector]
 #[derive(Debug, FunctionMeta)]
 pub struct PythonDetector;
 
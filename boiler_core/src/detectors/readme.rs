@@ -1,4 +1,4 @@
-use boiler_macros::FunctionMeta;
+use boiler_macros::{detector, FunctionMeta};
 use regex::Regex;
 
 use crate::context_keys;
@@ -6,6 +6,7 @@ use crate::data::{Repo, Value};
 use crate::detectors::{Detector, DetectorResult};
 
 /// Retrieves the project name from the README.md file.
+#[detector]
 #[derive(Debug, FunctionMeta)]
 pub struct ReadmeDetector;
 
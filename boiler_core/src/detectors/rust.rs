@@ -1,5 +1,4 @@
-use boiler_macros::FunctionMeta;
-use ignore::Walk;
+use boiler_macros::{detector, FunctionMeta};
 use path_slash::PathExt;
 use serde::Deserialize;
 
@@ -22,6 +21,7 @@ struct CargoPackage {
 
 /// Detects if the project contains Rust files, and retrieves basic metadata
 /// from Cargo.toml, such as authors or the crate name.
+#[detector]
 #[derive(Debug, FunctionMeta)]
 pub struct RustDetector;
 
@@ -67,18 +67,11 @@ impl Detector for RustDetector {
 
 impl RustDetector {
     fn detect_trunk(&self, repo: &Repo) -> DetectorResult {
-        let mut config_paths = vec![];
-
-        for entry in Walk::new(repo.path()) {
-            let path = entry?.path().to_owned();
-            if path.is_file() && path.file_name().unwrap_or_default() == "Trunk.toml" {
-                let path_on_repo = path
-                    .strip_prefix(repo.path())
-                    .expect("could not strip repo prefix")
-                    .to_owned();
-                config_paths.push(path_on_repo);
-            }
-        }
+        let config_paths: Vec<_> = repo
+            .files()?
+            .with_file_name("Trunk.toml")
+            .map(|file| file.path().to_owned())
+            .collect();
 
         if config_paths.is_empty() {
             Ok(Value::empty_object())
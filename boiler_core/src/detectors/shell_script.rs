@@ -1,10 +1,11 @@
-use boiler_macros::FunctionMeta;
+use boiler_macros::{detector, FunctionMeta};
 
 use crate::data::Repo;
 use crate::detectors::{Detector, DetectorResult};
 use crate::detectors_utils::detect_by_header;
 
 /// Detects if the project contains shell scripts.
+#[detector]
 #[derive(Debug, FunctionMeta)]
 pub struct ShellScriptDetector;
 
@@ -1,10 +1,11 @@
-use boiler_macros::FunctionMeta;
+use boiler_macros::{detector, FunctionMeta};
 
 use crate::data::Repo;
 use crate::detectors::{Detector, DetectorResult};
 use crate::detectors_utils::detect_by_extension;
 
 /// Detects if the project contains YAML files.
+#[detector]
 #[derive(Debug, FunctionMeta)]
 pub struct YamlDetector;
 
@@ -1,8 +1,3 @@
-use std::io::Read;
-use std::path::Path;
-
-use ignore::Walk;
-
 use crate::actions::ActionData;
 use crate::context_keys;
 use crate::data::{Repo, Value};
@@ -26,45 +21,26 @@ impl ActionDataExt for ActionData {
 }
 
 pub fn detect_by_extension(repo: &Repo, extensions: &[&str], lang: &str) -> DetectorResult {
-    detect_by_predicate(repo, lang, |path| {
-        if let Some(ext) = path.extension() {
-            if extensions.contains(&ext.to_ascii_lowercase().to_string_lossy().as_ref()) {
-                return Ok(true);
-            }
-        }
-        Ok(false)
-    })
+    let found = repo.files()?.with_extension(extensions).next().is_some();
+
+    Ok(lang_data(found, lang))
 }
 
 pub fn detect_by_header(repo: &Repo, headers: &[&[u8]], lang: &str) -> DetectorResult {
-    const BUFFER_SIZE: usize = 256;
-    let mut buf = [0; BUFFER_SIZE];
+    let found = repo.files()?.iter().any(|file| {
+        headers
+            .iter()
+            .any(|header| file.header().starts_with(header))
+    });
 
-    detect_by_predicate(repo, lang, |path| {
-        let mut file = std::fs::File::open(path)?;
-        let _ = file.read(&mut buf)?;
-        for header in headers {
-            if buf.starts_with(header) {
-                return Ok(true);
-            }
-        }
-        Ok(false)
-    })
+    Ok(lang_data(found, lang))
 }
 
-fn detect_by_predicate(
-    repo: &Repo,
-    lang: &str,
-    mut predicate: impl FnMut(&Path) -> std::io::Result<bool>,
-) -> DetectorResult {
-    for entry in Walk::new(repo.path()) {
-        let path = entry?.path().to_owned();
-        if path.is_file() && predicate(&path)? {
-            let mut data = Value::empty_object();
-            data.insert(context_keys::LANGS, vec![Value::new_string(lang)]);
-            return Ok(data);
-        }
+fn lang_data(found: bool, lang: &str) -> Value {
+    let mut data = Value::empty_object();
+    if found {
+        data.insert(context_keys::LANGS, vec![Value::new_string(lang)]);
     }
 
-    Ok(Value::empty_object())
+    data
 }
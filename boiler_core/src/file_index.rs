@@ -0,0 +1,179 @@
+use std::ffi::OsStr;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use ignore::Walk;
+use log::{debug, warn};
+
+/// The number of bytes from the beginning of each file kept in the index.
+pub const HEADER_SIZE: usize = 256;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FileIndexError {
+    #[error("Could not walk the repository")]
+    Walk(#[from] ignore::Error),
+}
+
+/// The files of a repository, found in a single walk of the directory tree.
+///
+/// The files ignored by `.gitignore` and similar files are skipped, as well as
+/// the files that cannot be read.
+#[derive(Debug, Clone, Default)]
+pub struct FileIndex {
+    files: Vec<IndexedFile>,
+}
+
+impl FileIndex {
+    pub fn build(root: &Path) -> Result<Self, FileIndexError> {
+        debug!("Indexing files in {}", root.display());
+        let mut files = Vec::new();
+
+        for entry in Walk::new(root) {
+            let full_path = entry?.path().to_owned();
+            if !full_path.is_file() {
+                continue;
+            }
+
+            let mut header = Vec::with_capacity(HEADER_SIZE);
+            if let Err(error) = std::fs::File::open(&full_path)
+                .and_then(|file| file.take(HEADER_SIZE as u64).read_to_end(&mut header))
+            {
+                warn!("Skipping file {}: {}", full_path.display(), error);
+                continue;
+            }
+            let path = full_path
+                .strip_prefix(root)
+                .expect("could not strip repo prefix")
+                .to_owned();
+
+            files.push(IndexedFile::new(path, header));
+        }
+
+        Ok(Self { files })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &IndexedFile> {
+        self.files.iter()
+    }
+
+    /// Returns the files with one of the given extensions; the extensions are
+    /// compared case-insensitively and must be given in lowercase.
+    pub fn with_extension<'a>(
+        &'a self,
+        extensions: &'a [&str],
+    ) -> impl Iterator<Item = &'a IndexedFile> {
+        self.iter().filter(move |file| {
+            file.extension()
+                .is_some_and(|extension| extensions.contains(&extension))
+        })
+    }
+
+    /// Returns the files with the given name, in any directory.
+    pub fn with_file_name<'a>(
+        &'a self,
+        file_name: &'a str,
+    ) -> impl Iterator<Item = &'a IndexedFile> {
+        self.iter()
+            .filter(move |file| file.path().file_name() == Some(OsStr::new(file_name)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedFile {
+    path: PathBuf,
+    extension: Option<String>,
+    header: Vec<u8>,
+}
+
+impl IndexedFile {
+    #[must_use]
+    fn new(path: PathBuf, header: Vec<u8>) -> Self {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+
+        Self {
+            path,
+            extension,
+            header,
+        }
+    }
+
+    /// Returns the path of the file, relative to the repository root.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the extension of the file, in lowercase.
+    #[must_use]
+    pub fn extension(&self) -> Option<&str> {
+        self.extension.as_deref()
+    }
+
+    /// Returns up to [`HEADER_SIZE`] bytes from the beginning of the file.
+    #[must_use]
+    pub fn header(&self) -> &[u8] {
+        &self.header
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::file_index::{FileIndex, IndexedFile, HEADER_SIZE};
+    use crate::test_utils::TempRepo;
+
+    #[test]
+    fn test_build() {
+        let repo = TempRepo::new();
+        repo.write_str("a/Test.RS", "fn main() {}");
+        repo.write_str("b/Trunk.toml", "");
+        repo.write_str("large.txt", &"x".repeat(HEADER_SIZE * 2));
+
+        let index = FileIndex::build(repo.path()).unwrap();
+
+        assert_eq!(index.iter().count(), 3);
+        let rust_files: Vec<_> = index.with_extension(&["rs"]).collect();
+        assert_eq!(rust_files.len(), 1);
+        assert_eq!(rust_files[0].path(), Path::new("a/Test.RS"));
+        assert_eq!(rust_files[0].header(), b"fn main() {}");
+        assert_eq!(
+            index.with_file_name("Trunk.toml").next().unwrap().path(),
+            Path::new("b/Trunk.toml")
+        );
+        assert_eq!(
+            index
+                .with_extension(&["txt"])
+                .next()
+                .unwrap()
+                .header()
+                .len(),
+            HEADER_SIZE
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_build_unreadable_file() {
+        use std::fs::Permissions;
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let repo = TempRepo::new();
+        repo.write_str("main.rs", "fn main() {}");
+        repo.write_str("secret.txt", "");
+        let secret_path = repo.path().join("secret.txt");
+        // The files are owned by the effective user, and root can read them
+        // regardless of their permissions
+        if std::fs::metadata(&secret_path).unwrap().uid() == 0 {
+            return;
+        }
+        std::fs::set_permissions(&secret_path, Permissions::from_mode(0o000)).unwrap();
+
+        let index = FileIndex::build(repo.path()).unwrap();
+
+        let paths: Vec<_> = index.iter().map(IndexedFile::path).collect();
+        assert_eq!(paths, [Path::new("main.rs")]);
+    }
+}
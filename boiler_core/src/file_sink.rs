@@ -0,0 +1,380 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::debug;
+use path_slash::PathExt;
+use similar::TextDiff;
+
+use crate::data::Repo;
+use crate::managed_regions::ManagedRegionError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ActionIoError {
+    #[error("Could not create directory: {path}")]
+    CreateDir {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Could not write file: {path}")]
+    WriteFile {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Could not remove file: {path}")]
+    RemoveFile {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Could not read file: {path}")]
+    ReadFile {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid managed regions in file: {path}")]
+    ManagedRegions {
+        path: PathBuf,
+        #[source]
+        source: ManagedRegionError,
+    },
+}
+
+impl ActionIoError {
+    #[must_use]
+    fn new_create_dir(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Self::CreateDir {
+            path: path.into(),
+            source,
+        }
+    }
+
+    #[must_use]
+    fn new_write_file(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Self::WriteFile {
+            path: path.into(),
+            source,
+        }
+    }
+
+    #[must_use]
+    fn new_remove_file(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Self::RemoveFile {
+            path: path.into(),
+            source,
+        }
+    }
+
+    #[must_use]
+    pub(crate) fn new_read_file(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Self::ReadFile {
+            path: path.into(),
+            source,
+        }
+    }
+
+    #[must_use]
+    pub(crate) fn new_managed_regions(
+        path: impl Into<PathBuf>,
+        source: ManagedRegionError,
+    ) -> Self {
+        Self::ManagedRegions {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+/// Destination for the files generated by actions.
+///
+/// The paths passed to the methods are relative to the root of the
+/// repository.
+pub trait FileSink: Debug + Send + Sync {
+    fn write_file(&self, repo: &Repo, path: &Path, content: &str) -> Result<(), ActionIoError>;
+
+    fn remove_file(&self, repo: &Repo, path: &Path) -> Result<(), ActionIoError>;
+}
+
+/// Writes the files directly to the disk, skipping the ones that would not
+/// change.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DiskFileSink;
+
+impl FileSink for DiskFileSink {
+    fn write_file(&self, repo: &Repo, path: &Path, content: &str) -> Result<(), ActionIoError> {
+        let full_path = repo.path().join(path);
+        let parent_dir = full_path.parent().expect("file path has no parent");
+
+        std::fs::create_dir_all(parent_dir)
+            .map_err(|e| ActionIoError::new_create_dir(parent_dir, e))?;
+        if let Ok(old_content) = std::fs::read_to_string(&full_path) {
+            if old_content == content {
+                debug!("File {} unchanged", full_path.display());
+                return Ok(());
+            }
+        }
+
+        debug!("Writing {} bytes to {}", content.len(), full_path.display());
+        std::fs::write(&full_path, content)
+            .map_err(|e| ActionIoError::new_write_file(full_path, e))?;
+
+        Ok(())
+    }
+
+    fn remove_file(&self, repo: &Repo, path: &Path) -> Result<(), ActionIoError> {
+        let full_path = repo.path().join(path);
+
+        debug!("Removing {}", full_path.display());
+        std::fs::remove_file(&full_path)
+            .map_err(|e| ActionIoError::new_remove_file(full_path, e))?;
+
+        Ok(())
+    }
+}
+
+/// Collects the files in memory without touching the disk, so that they can be
+/// compared with the current state of the repository.
+#[derive(Debug, Default)]
+pub struct MemoryFileSink {
+    files: Mutex<BTreeMap<PathBuf, String>>,
+    removed_files: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl MemoryFileSink {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn files(&self) -> BTreeMap<PathBuf, String> {
+        self.files.lock().expect("file sink lock poisoned").clone()
+    }
+
+    #[must_use]
+    pub fn removed_files(&self) -> BTreeSet<PathBuf> {
+        self.removed_files
+            .lock()
+            .expect("file sink lock poisoned")
+            .clone()
+    }
+
+    /// Returns the files whose collected content differs from the one on the
+    /// disk.
+    pub fn changes(&self, repo: &Repo) -> Result<Vec<FileChange>, ActionIoError> {
+        let mut changes = Vec::new();
+
+        for (path, new_content) in self.files() {
+            let full_path = repo.path().join(&path);
+            let old_content = if full_path.exists() {
+                Some(
+                    std::fs::read_to_string(&full_path)
+                        .map_err(|e| ActionIoError::new_read_file(&full_path, e))?,
+                )
+            } else {
+                None
+            };
+
+            if old_content.as_deref() != Some(new_content.as_str()) {
+                changes.push(FileChange {
+                    path,
+                    old_content,
+                    new_content: Some(new_content),
+                });
+            }
+        }
+
+        for path in self.removed_files() {
+            let full_path = repo.path().join(&path);
+            let old_content = std::fs::read_to_string(&full_path)
+                .map_err(|e| ActionIoError::new_read_file(&full_path, e))?;
+            changes.push(FileChange {
+                path,
+                old_content: Some(old_content),
+                new_content: None,
+            });
+        }
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(changes)
+    }
+}
+
+impl FileSink for MemoryFileSink {
+    fn write_file(&self, _repo: &Repo, path: &Path, content: &str) -> Result<(), ActionIoError> {
+        debug!("Collecting {} bytes for {}", content.len(), path.display());
+        self.files
+            .lock()
+            .expect("file sink lock poisoned")
+            .insert(path.to_owned(), content.to_owned());
+
+        Ok(())
+    }
+
+    fn remove_file(&self, _repo: &Repo, path: &Path) -> Result<(), ActionIoError> {
+        debug!("Collecting removal of {}", path.display());
+        self.files
+            .lock()
+            .expect("file sink lock poisoned")
+            .remove(path);
+        self.removed_files
+            .lock()
+            .expect("file sink lock poisoned")
+            .insert(path.to_owned());
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    path: PathBuf,
+    old_content: Option<String>,
+    new_content: Option<String>,
+}
+
+impl FileChange {
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[must_use]
+    pub fn old_content(&self) -> Option<&str> {
+        self.old_content.as_deref()
+    }
+
+    /// Returns the new content of the file, or `None` if it is removed.
+    #[must_use]
+    pub fn new_content(&self) -> Option<&str> {
+        self.new_content.as_deref()
+    }
+
+    #[must_use]
+    pub fn is_new_file(&self) -> bool {
+        self.old_content.is_none()
+    }
+
+    #[must_use]
+    pub fn is_removed(&self) -> bool {
+        self.new_content.is_none()
+    }
+
+    #[must_use]
+    pub fn unified_diff(&self) -> String {
+        let path = self.path.to_slash_lossy();
+        let old_header = if self.is_new_file() {
+            "/dev/null".to_owned()
+        } else {
+            format!("a/{path}")
+        };
+        let new_header = if self.is_removed() {
+            "/dev/null".to_owned()
+        } else {
+            format!("b/{path}")
+        };
+
+        TextDiff::from_lines(
+            self.old_content().unwrap_or_default(),
+            self.new_content().unwrap_or_default(),
+        )
+        .unified_diff()
+        .header(&old_header, &new_header)
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::file_sink::{DiskFileSink, FileSink, MemoryFileSink};
+    use crate::test_utils::TempRepo;
+
+    #[test]
+    fn test_disk_sink_write() {
+        let repo = TempRepo::new();
+
+        DiskFileSink
+            .write_file(&repo.repo(), Path::new("dir/test.txt"), "test")
+            .unwrap();
+
+        assert_eq!(repo.read_str("dir/test.txt"), "test");
+    }
+
+    #[test]
+    fn test_memory_sink_does_not_write() {
+        let repo = TempRepo::new();
+        let sink = MemoryFileSink::new();
+
+        sink.write_file(&repo.repo(), Path::new("test.txt"), "test")
+            .unwrap();
+
+        assert!(repo.is_empty());
+        assert_eq!(sink.files().len(), 1);
+    }
+
+    #[test]
+    fn test_memory_sink_changes() {
+        let repo = TempRepo::new();
+        repo.write_str("unchanged.txt", "same\n");
+        repo.write_str("changed.txt", "old\n");
+        let sink = MemoryFileSink::new();
+
+        sink.write_file(&repo.repo(), Path::new("unchanged.txt"), "same\n")
+            .unwrap();
+        sink.write_file(&repo.repo(), Path::new("changed.txt"), "new\n")
+            .unwrap();
+        sink.write_file(&repo.repo(), Path::new("created.txt"), "created\n")
+            .unwrap();
+
+        let changes = sink.changes(&repo.repo()).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].path(), Path::new("changed.txt"));
+        assert_eq!(
+            changes[0].unified_diff(),
+            "--- a/changed.txt\n+++ b/changed.txt\n@@ -1 +1 @@\n-old\n+new\n"
+        );
+        assert_eq!(changes[1].path(), Path::new("created.txt"));
+        assert!(changes[1].is_new_file());
+        assert_eq!(
+            changes[1].unified_diff(),
+            "--- /dev/null\n+++ b/created.txt\n@@ -0,0 +1 @@\n+created\n"
+        );
+    }
+
+    #[test]
+    fn test_memory_sink_removed() {
+        let repo = TempRepo::new();
+        repo.write_str("removed.txt", "old\n");
+        let sink = MemoryFileSink::new();
+
+        sink.remove_file(&repo.repo(), Path::new("removed.txt"))
+            .unwrap();
+
+        assert!(repo.file_not_empty("removed.txt"));
+        let changes = sink.changes(&repo.repo()).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].is_removed());
+        assert_eq!(
+            changes[0].unified_diff(),
+            "--- a/removed.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-old\n"
+        );
+    }
+
+    #[test]
+    fn test_disk_sink_remove() {
+        let repo = TempRepo::new();
+        repo.write_str("test.txt", "test");
+
+        DiskFileSink
+            .remove_file(&repo.repo(), Path::new("test.txt"))
+            .unwrap();
+
+        assert!(repo.is_empty());
+    }
+}
@@ -1,17 +1,17 @@
 use std::collections::BTreeMap;
 
 pub trait FunctionMeta {
-    fn name(&self) -> &'static str;
-    fn description(&self) -> &'static str;
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
     fn default_enabled(&self) -> bool;
 }
 
 impl<T: FunctionMeta> FunctionMeta for &T {
-    fn name(&self) -> &'static str {
+    fn name(&self) -> &str {
         (*self).name()
     }
 
-    fn description(&self) -> &'static str {
+    fn description(&self) -> &str {
         (*self).description()
     }
 
@@ -0,0 +1,469 @@
+//! Committing the files changed by [`run_in_repo`](crate::run_in_repo) to the
+//! git repository.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use gix::bstr::{BStr, ByteSlice};
+use gix::index::entry::{Flags, Mode, Stage, Stat};
+use gix::objs::tree::EntryKind;
+use gix::refs::transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog};
+use gix::refs::Target;
+use gix::{ObjectId, Repository};
+use log::info;
+use path_slash::PathExt;
+
+use crate::data::Repo;
+use crate::lock::LOCK_FILENAME;
+use crate::report::RunReport;
+
+/// The first line of the messages of the commits made by boiler.
+pub const COMMIT_SUBJECT: &str = "chore: update boilerplate files";
+
+/// Fails if the tracked files in the repository have uncommitted changes.
+///
+/// Untracked files are not taken into account.
+pub fn ensure_clean(repo: &Repo) -> anyhow::Result<()> {
+    let repository = open(repo)?;
+    if repository.head()?.is_unborn() {
+        return Ok(());
+    }
+
+    let is_dirty = repository
+        .is_dirty()
+        .context("Could not get the status of the git repo")?;
+    if is_dirty {
+        anyhow::bail!(
+            "The working tree of {} has uncommitted changes",
+            repo.path().display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns the message of the commit with the changes from `report`,
+/// listing the actions that changed something.
+#[must_use]
+pub fn commit_message(report: &RunReport) -> String {
+    let mut message = format!("{COMMIT_SUBJECT}\n\n");
+
+    for action in &report.actions {
+        let changed: Vec<_> = action.changed().map(|file| file.path.as_path()).collect();
+        if !changed.is_empty() {
+            message.push_str(&format!("* {}: {}\n", action.action, join_paths(&changed)));
+        }
+    }
+    if !report.pruned.is_empty() {
+        let pruned: Vec<_> = report.pruned.iter().map(PathBuf::as_path).collect();
+        message.push_str(&format!(
+            "* Removed the files that are no longer generated: {}\n",
+            join_paths(&pruned)
+        ));
+    }
+    if report.lock_changed && !report.has_changes() {
+        message.push_str(&format!(
+            "* Updated {LOCK_FILENAME} for the changed context\n"
+        ));
+    }
+
+    message
+}
+
+fn join_paths(paths: &[&Path]) -> String {
+    paths
+        .iter()
+        .map(|path| path.to_slash_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Stages the files changed and removed according to `report`, along with the
+/// [`LOCK_FILENAME`], and commits them with [`commit_message`].
+///
+/// If `branch` is given, the commit is made on a new branch created from
+/// `HEAD`, which is then checked out; otherwise, it is made on the current
+/// branch. The other changes in the index are left as they are.
+///
+/// The files are committed as they are on disk, so if they had uncommitted
+/// changes before boiler touched them (which [`ensure_clean`] prevents), these
+/// changes end up in the commit as well.
+///
+/// Returns the ID of the commit, or `None` if there was nothing to commit.
+pub fn commit_changes(
+    repo: &Repo,
+    report: &RunReport,
+    branch: Option<&str>,
+) -> anyhow::Result<Option<ObjectId>> {
+    if !report.has_changes() && !report.lock_changed {
+        return Ok(None);
+    }
+
+    let repository = open(repo)?;
+    let branch_ref = branch.map(|branch| format!("refs/heads/{branch}"));
+    if let Some(branch_ref) = &branch_ref {
+        if repository
+            .try_find_reference(branch_ref.as_str())?
+            .is_some()
+        {
+            anyhow::bail!("Branch {} already exists", branch.unwrap_or_default());
+        }
+    }
+
+    let mut index = repository.index_or_empty()?.as_ref().clone();
+    let mut editor = repository.edit_tree(repository.head_tree_id_or_empty()?)?;
+
+    let mut updated = report.changed();
+    if repo.path().join(LOCK_FILENAME).exists() {
+        updated.push(PathBuf::from(LOCK_FILENAME));
+    }
+    for path in &updated {
+        let (id, mode) = stage_file(&repository, &mut index, repo.path(), path)?;
+        let kind = if mode == Mode::FILE_EXECUTABLE {
+            EntryKind::BlobExecutable
+        } else {
+            EntryKind::Blob
+        };
+        editor.upsert(path.to_slash_lossy().as_ref(), kind, id)?;
+    }
+    for path in &report.pruned {
+        let rela_path = path.to_slash_lossy();
+        editor.remove(rela_path.as_ref())?;
+        index.remove_entries(|_, entry_path, _| entry_path == rela_path.as_bytes().as_bstr());
+    }
+    let tree_id = editor.write()?;
+
+    let parent = repository.head_id().ok().map(|id| id.detach());
+    let commit_id = repository
+        .commit(
+            branch_ref.as_deref().unwrap_or("HEAD"),
+            commit_message(report),
+            tree_id,
+            parent,
+        )
+        .context("Could not create the commit")?
+        .detach();
+    if let Some(branch_ref) = branch_ref {
+        checkout_branch(&repository, &branch_ref)?;
+    }
+    index
+        .write(gix::index::write::Options::default())
+        .context("Could not write the git index")?;
+
+    info!("Committed the changes as {commit_id}");
+    Ok(Some(commit_id))
+}
+
+/// Writes the file at `path` to the object database and updates its entry in
+/// `index`; returns the ID of the blob and the mode of the file.
+fn stage_file(
+    repository: &Repository,
+    index: &mut gix::index::File,
+    repo_path: &Path,
+    path: &Path,
+) -> anyhow::Result<(ObjectId, Mode)> {
+    let full_path = repo_path.join(path);
+    let content = std::fs::read(&full_path)
+        .with_context(|| format!("Could not read {}", full_path.display()))?;
+    let id = repository.write_blob(content)?.detach();
+    let metadata = gix::index::fs::Metadata::from_path_no_follow(&full_path)?;
+    let stat = Stat::from_fs(&metadata)?;
+    let mode = if metadata.is_executable() {
+        Mode::FILE_EXECUTABLE
+    } else {
+        Mode::FILE
+    };
+
+    let rela_path = path.to_slash_lossy();
+    let rela_path: &BStr = rela_path.as_bytes().as_bstr();
+    if let Some(entry) = index.entry_mut_by_path_and_stage(rela_path, Stage::Unconflicted) {
+        entry.id = id;
+        entry.stat = stat;
+        // The executable bit is not stored in the file system on Windows, so
+        // the mode of the tracked files is kept there
+        if cfg!(unix) {
+            entry.mode = mode;
+        }
+        return Ok((id, entry.mode));
+    }
+
+    index.dangerously_push_entry(stat, id, Flags::empty(), mode, rela_path);
+    index.sort_entries();
+    Ok((id, mode))
+}
+
+/// Points `HEAD` to `branch_ref`; the branch must point to a commit with the
+/// same files as the working tree.
+fn checkout_branch(repository: &Repository, branch_ref: &str) -> anyhow::Result<()> {
+    repository
+        .edit_reference(RefEdit {
+            change: Change::Update {
+                log: LogChange {
+                    mode: RefLog::AndReference,
+                    force_create_reflog: false,
+                    message: format!("checkout: moving to {branch_ref}").into(),
+                },
+                expected: PreviousValue::Any,
+                new: Target::Symbolic(branch_ref.try_into()?),
+            },
+            name: "HEAD".try_into()?,
+            deref: false,
+        })
+        .with_context(|| format!("Could not check out {branch_ref}"))?;
+
+    Ok(())
+}
+
+fn open(repo: &Repo) -> anyhow::Result<Repository> {
+    gix::open(repo.path()).with_context(|| "Could not open git repo")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use gix::Repository;
+
+    use crate::actions::ACTIONS;
+    use crate::context::REPO_CONFIG_FILENAME;
+    use crate::git::{commit_changes, commit_message, ensure_clean};
+    use crate::lock::LOCK_FILENAME;
+    use crate::report::{ActionReport, ActionStatus, FileReport, FileStatus, RunReport};
+    use crate::test_utils::TempRepo;
+    use crate::{Boiler, RunOptions};
+
+    #[test]
+    fn test_commit_message() {
+        let report = create_report();
+
+        assert_eq!(
+            commit_message(&report),
+            "chore: update boilerplate files\n\n\
+             * RustCi: .github/workflows/rust.yml\n\
+             * Readme: README.md\n\
+             * Removed the files that are no longer generated: old.txt\n"
+        );
+    }
+
+    #[test]
+    fn test_ensure_clean() {
+        let temp_repo = create_git_repo();
+        ensure_clean(&temp_repo.repo()).unwrap();
+
+        temp_repo.write_str("untracked.txt", "untracked");
+        ensure_clean(&temp_repo.repo()).unwrap();
+
+        temp_repo.write_str("README.md", "modified");
+        assert!(ensure_clean(&temp_repo.repo()).is_err());
+    }
+
+    #[test]
+    fn test_commit_changes() {
+        let temp_repo = create_git_repo();
+        temp_repo.write_str(".github/workflows/rust.yml", "jobs: {}\n");
+        temp_repo.write_str("README.md", "# test\n");
+        temp_repo.write_str(LOCK_FILENAME, "files: {}\n");
+        std::fs::remove_file(temp_repo.path().join("old.txt")).unwrap();
+
+        let report = create_report();
+        let commit_id = commit_changes(&temp_repo.repo(), &report, None).unwrap();
+
+        assert!(commit_id.is_some());
+        ensure_clean(&temp_repo.repo()).unwrap();
+        let repository = open(&temp_repo);
+        assert_eq!(
+            repository
+                .head_commit()
+                .unwrap()
+                .message_raw()
+                .unwrap()
+                .to_string(),
+            commit_message(&report)
+        );
+        assert_eq!(
+            head_files(&repository),
+            [
+                (".boiler.lock".to_owned(), "files: {}\n".to_owned()),
+                (
+                    ".github/workflows/rust.yml".to_owned(),
+                    "jobs: {}\n".to_owned()
+                ),
+                ("README.md".to_owned(), "# test\n".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commit_changes_on_branch() {
+        let temp_repo = create_git_repo();
+        temp_repo.write_str("README.md", "# test\n");
+        let report = RunReport {
+            actions: vec![action_report("Readme", "README.md", FileStatus::Updated)],
+            ..RunReport::default()
+        };
+
+        commit_changes(&temp_repo.repo(), &report, Some("boiler")).unwrap();
+
+        let repository = open(&temp_repo);
+        assert_eq!(repository.head_name().unwrap().unwrap().shorten(), "boiler");
+        assert_eq!(commit_count(&repository), 2);
+        ensure_clean(&temp_repo.repo()).unwrap();
+        assert!(commit_changes(&temp_repo.repo(), &report, Some("boiler")).is_err());
+    }
+
+    #[test]
+    fn test_commit_no_changes() {
+        let temp_repo = create_git_repo();
+        let report = RunReport {
+            actions: vec![action_report("Readme", "README.md", FileStatus::Unchanged)],
+            ..RunReport::default()
+        };
+
+        let commit_id = commit_changes(&temp_repo.repo(), &report, Some("boiler")).unwrap();
+
+        assert_eq!(commit_id, None);
+        assert_eq!(commit_count(&open(&temp_repo)), 1);
+    }
+
+    #[test]
+    fn test_commit_lock_only() {
+        let temp_repo = create_git_repo();
+        temp_repo.write_str("Cargo.toml", "[package]\nname = \"test\"\n");
+        let mut builder = Boiler::builder();
+        for action in ACTIONS
+            .iter()
+            .filter(|action| action.name() != "RustfmtToml")
+        {
+            builder = builder.remove_action(action.name());
+        }
+        let boiler = builder.build().unwrap();
+        let report = boiler
+            .run(temp_repo.repo(), &RunOptions::default())
+            .unwrap();
+        commit_changes(&temp_repo.repo(), &report, None).unwrap();
+
+        temp_repo.write_str(
+            REPO_CONFIG_FILENAME,
+            "context:\n  coverage_enabled: false\n",
+        );
+        let report = boiler
+            .run(temp_repo.repo(), &RunOptions::default())
+            .unwrap();
+        assert!(!report.has_changes());
+        assert!(report.lock_changed);
+        let commit_id = commit_changes(&temp_repo.repo(), &report, None).unwrap();
+
+        assert!(commit_id.is_some());
+        let repository = open(&temp_repo);
+        assert_eq!(commit_count(&repository), 3);
+        assert!(head_files(&repository)
+            .contains(&(LOCK_FILENAME.to_owned(), temp_repo.read_str(LOCK_FILENAME))));
+        ensure_clean(&temp_repo.repo()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commit_executable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_repo = create_git_repo();
+        temp_repo.write_str("build.sh", "#!/bin/sh\n");
+        let path = temp_repo.path().join("build.sh");
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let report = RunReport {
+            actions: vec![action_report("Build", "build.sh", FileStatus::Created)],
+            ..RunReport::default()
+        };
+
+        commit_changes(&temp_repo.repo(), &report, None).unwrap();
+
+        let repository = open(&temp_repo);
+        let tree = repository.head_commit().unwrap().tree().unwrap();
+        let entry = tree.find_entry("build.sh").unwrap();
+        assert!(entry.mode().is_executable());
+        ensure_clean(&temp_repo.repo()).unwrap();
+    }
+
+    fn create_report() -> RunReport {
+        RunReport {
+            actions: vec![
+                action_report("RustCi", ".github/workflows/rust.yml", FileStatus::Created),
+                action_report("Readme", "README.md", FileStatus::Updated),
+                action_report("License", "LICENSE", FileStatus::Unchanged),
+            ],
+            pruned: vec![PathBuf::from("old.txt")],
+            ..RunReport::default()
+        }
+    }
+
+    fn action_report(action: &str, path: &str, status: FileStatus) -> ActionReport {
+        ActionReport {
+            action: action.to_owned(),
+            status: ActionStatus::Ran,
+            files: vec![FileReport::new(Path::new(path), status)],
+            duration: Duration::ZERO,
+        }
+    }
+
+    fn create_git_repo() -> TempRepo {
+        let temp_repo = TempRepo::new();
+        gix::init(temp_repo.path()).unwrap();
+        let config_path = temp_repo.path().join(".git/config");
+        let config = std::fs::read_to_string(&config_path).unwrap();
+        std::fs::write(
+            config_path,
+            format!("{config}[user]\n\tname = Test\n\temail = test@example.com\n"),
+        )
+        .unwrap();
+        temp_repo.write_str("README.md", "# old\n");
+        temp_repo.write_str("old.txt", "old\n");
+        let report = RunReport {
+            actions: vec![
+                action_report("Readme", "README.md", FileStatus::Created),
+                action_report("Old", "old.txt", FileStatus::Created),
+            ],
+            ..RunReport::default()
+        };
+        commit_changes(&temp_repo.repo(), &report, None).unwrap();
+
+        temp_repo
+    }
+
+    fn open(temp_repo: &TempRepo) -> Repository {
+        gix::open(temp_repo.path()).unwrap()
+    }
+
+    fn commit_count(repository: &Repository) -> usize {
+        repository
+            .head_id()
+            .unwrap()
+            .ancestors()
+            .all()
+            .unwrap()
+            .count()
+    }
+
+    /// Returns the paths and the contents of the files in the `HEAD` commit,
+    /// sorted by path.
+    fn head_files(repository: &Repository) -> Vec<(String, String)> {
+        let tree = repository.head_commit().unwrap().tree().unwrap();
+        let mut recorder = gix::traverse::tree::Recorder::default();
+        tree.traverse().breadthfirst(&mut recorder).unwrap();
+
+        let mut files: Vec<_> = recorder
+            .records
+            .into_iter()
+            .filter(|entry| entry.mode.is_blob())
+            .map(|entry| {
+                let blob = repository.find_blob(entry.oid).unwrap();
+                (
+                    entry.filepath.to_string(),
+                    String::from_utf8(blob.data.clone()).unwrap(),
+                )
+            })
+            .collect();
+        files.sort();
+        files
+    }
+}
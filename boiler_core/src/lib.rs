@@ -1,58 +1,101 @@
-use anyhow::Context;
-use log::info;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-use crate::actions::ActionData;
-use crate::context::{RepoConfig, ReposConfig};
+use crate::context::{ContextStage, ReposConfig};
 use crate::data::{Repo, Value};
-use crate::detectors::{create_detectors_enabled, detect_with_defaults};
+use crate::file_sink::{DiskFileSink, FileSink};
+use crate::provenance::Provenance;
+use crate::report::RunReport;
 
 pub mod actions;
 mod actions_utils;
 pub mod context;
 mod context_keys;
+pub mod context_schema;
 pub mod data;
 pub mod detectors;
 mod detectors_utils;
+pub mod file_index;
+pub mod file_sink;
 pub mod function_meta;
+pub mod git;
+pub mod lock;
+pub mod managed_regions;
+pub mod plugins;
+pub mod provenance;
+pub mod report;
+pub mod runner;
+pub mod scaffold;
 mod template_renderer;
+pub mod templates;
 #[cfg(test)]
 mod test_utils;
 mod time;
 
-pub fn run_in_repo(repo: Repo) -> anyhow::Result<()> {
-    let repo_path = repo.path().to_owned();
+#[doc(hidden)]
+pub use inventory;
+pub use runner::{Boiler, BoilerBuilder};
 
-    let mut data =
-        Ok::<Value, anyhow::Error>(detect_with_defaults(&repo, &create_detectors_enabled())?)
-            .with_context(|| format!("Could not build context for {}", repo_path.display()))?;
-    let repo_string = data["repo_owner"].as_string().unwrap().to_owned()
-        + "/"
-        + data["repo_name"].as_string().unwrap();
-    info!("Detected context:\n{}", data.as_yaml());
-
-    let mut repo_config = RepoConfig::default();
+/// Options for [`run_in_repo`].
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    /// Where the generated files are written to.
+    pub sink: Arc<dyn FileSink>,
+    /// The directory with the templates overriding the built-in ones; takes
+    /// priority over the one set in the config.
+    pub templates_dir: Option<PathBuf>,
+    /// Whether to remove the files generated by the previous runs that are no
+    /// longer generated by any action.
+    pub prune: bool,
+    /// Whether the changes made to the generated files since they were last
+    /// generated are merged into the new content. If not set, the files are
+    /// replaced with the generated content, so that the changes show up as
+    /// differences, like `boiler check` needs.
+    pub keep_local_changes: bool,
+}
 
-    let repos_config = ReposConfig::from_yaml_string(include_str!("overrides.yml"));
-    if let Some(repo_override) = repos_config.get(&repo_string) {
-        repo_config = repo_config.override_with(repo_override);
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            sink: Arc::new(DiskFileSink),
+            templates_dir: None,
+            prune: false,
+            keep_local_changes: true,
+        }
     }
+}
 
-    info!(
-        "Overriding context for {} with:\n{}",
-        repo_string,
-        repo_config.context().as_yaml()
-    );
-    data.override_with(repo_config.context());
+/// Runs the actions in the repository and updates its
+/// [`LOCK_FILENAME`](lock::LOCK_FILENAME).
+///
+/// Uses the built-in detectors and actions; see [`Boiler`] to add custom ones.
+pub fn run_in_repo(
+    repo: Repo,
+    repos_config: &ReposConfig,
+    options: &RunOptions,
+) -> anyhow::Result<RunReport> {
+    create_boiler(repos_config)?.run(repo, options)
+}
 
-    info!("New context:\n{}", data.as_yaml());
+/// Builds the context of the repository, as seen at the given stage.
+pub fn build_context(
+    repo: &Repo,
+    repos_config: &ReposConfig,
+    stage: ContextStage,
+) -> anyhow::Result<Value> {
+    create_boiler(repos_config)?.build_context(repo, stage)
+}
 
-    let actions_enabled = repo_config.create_actions_enabled(&actions::create_actions_enabled())?;
-    let action_data = ActionData {
-        repo,
-        context: data,
-    };
-    actions::run_actions(&action_data, &actions_enabled)
-        .with_context(|| format!("Could not run actions for {}", repo_path.display()))?;
+/// Builds the context like [`build_context`], keeping track of where each
+/// value comes from.
+pub fn build_context_with_provenance(
+    repo: &Repo,
+    repos_config: &ReposConfig,
+    stage: ContextStage,
+) -> anyhow::Result<(Value, Provenance)> {
+    create_boiler(repos_config)?.build_context_with_provenance(repo, stage)
+}
 
-    Ok(())
+fn create_boiler(repos_config: &ReposConfig) -> anyhow::Result<Boiler> {
+    Boiler::builder().repos_config(repos_config.clone()).build()
 }
@@ -0,0 +1,436 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::{debug, warn};
+use path_slash::PathExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use similar::TextMerge;
+
+use crate::data::{Repo, Value};
+use crate::report::{FileReport, FileStatus};
+
+/// Name of the file recording what boiler generated, read from and written to
+/// the repository root.
+pub const LOCK_FILENAME: &str = ".boiler.lock";
+
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    #[error("Could not read lock file: {path}")]
+    ReadFile {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Could not parse lock file: {path}")]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: serde_yaml::Error,
+    },
+}
+
+/// The files generated by the last run, keyed by their paths relative to the
+/// repository root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lock {
+    #[serde(default)]
+    files: BTreeMap<String, LockEntry>,
+}
+
+impl Lock {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the [`LOCK_FILENAME`] file from the repository root; returns an
+    /// empty lock if it does not exist.
+    pub fn from_repo(repo: &Repo) -> Result<Self, LockError> {
+        let path = repo.path().join(LOCK_FILENAME);
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+
+        let yaml_string = std::fs::read_to_string(&path).map_err(|source| LockError::ReadFile {
+            path: path.clone(),
+            source,
+        })?;
+
+        serde_yaml::from_str(&yaml_string).map_err(|source| LockError::Parse { path, source })
+    }
+
+    #[must_use]
+    pub fn to_yaml_string(&self) -> String {
+        serde_yaml::to_string(self).expect("could not serialize lock")
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    #[must_use]
+    pub fn get(&self, path: &Path) -> Option<&LockEntry> {
+        self.files.get(path.to_slash_lossy().as_ref())
+    }
+
+    pub fn insert(&mut self, path: &Path, entry: LockEntry) {
+        self.files.insert(path.to_slash_lossy().into_owned(), entry);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &LockEntry)> {
+        self.files
+            .iter()
+            .map(|(path, entry)| (path.as_str(), entry))
+    }
+}
+
+/// A single generated file.
+///
+/// Only the hash of the generated content is kept, so that the lock stays
+/// small; the content itself is kept in the [`OutputStore`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockEntry {
+    template: String,
+    context_hash: String,
+    output_hash: String,
+}
+
+impl LockEntry {
+    #[must_use]
+    pub fn new(template: &str, context: &Value, output: &str) -> Self {
+        Self {
+            template: template.to_owned(),
+            context_hash: hash(&context.as_json()),
+            output_hash: hash(output),
+        }
+    }
+
+    #[must_use]
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    #[must_use]
+    pub fn context_hash(&self) -> &str {
+        &self.context_hash
+    }
+
+    #[must_use]
+    pub fn output_hash(&self) -> &str {
+        &self.output_hash
+    }
+
+    /// Returns whether `content` differs from the generated content.
+    #[must_use]
+    pub fn is_modified(&self, content: &str) -> bool {
+        hash(content) != self.output_hash
+    }
+}
+
+/// Returns the hex-encoded SHA-256 hash of `content`.
+#[must_use]
+pub fn hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// The contents generated by the previous runs, keyed by their hashes, used as
+/// the common ancestors when merging the changes made by the user with the
+/// newly generated content.
+///
+/// The contents are kept in the git directory, as they are only needed
+/// locally and would make the [`LOCK_FILENAME`] grow with every generated
+/// file; without a directory, nothing is stored.
+#[derive(Debug, Clone, Default)]
+pub struct OutputStore {
+    dir: Option<PathBuf>,
+}
+
+impl OutputStore {
+    #[must_use]
+    pub fn new<T: Into<PathBuf>>(dir: T) -> Self {
+        Self {
+            dir: Some(dir.into()),
+        }
+    }
+
+    /// Returns the store in the git directory of the repository, or an empty
+    /// one if it is not a git repository.
+    #[must_use]
+    pub fn for_repo(repo: &Repo) -> Self {
+        match gix::open(repo.path()) {
+            Ok(repository) => Self::new(repository.git_dir().join("boiler").join("outputs")),
+            Err(error) => {
+                debug!(
+                    "Not storing the generated files of {}: {error}",
+                    repo.path().display()
+                );
+                Self::default()
+            }
+        }
+    }
+
+    /// Returns the content with the given [`hash`], if it has been stored.
+    #[must_use]
+    pub fn get(&self, hash: &str) -> Option<String> {
+        std::fs::read_to_string(self.dir.as_ref()?.join(hash)).ok()
+    }
+
+    /// Stores the content; failures are only logged, as the contents are only
+    /// needed to merge the local changes.
+    pub fn insert(&self, content: &str) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+
+        let path = dir.join(hash(content));
+        if path.exists() {
+            return;
+        }
+        if let Err(error) =
+            std::fs::create_dir_all(dir).and_then(|()| std::fs::write(&path, content))
+        {
+            warn!("Could not store {}: {error}", path.display());
+        }
+    }
+
+    /// Removes the contents whose hashes are not in `hashes`.
+    pub fn retain(&self, hashes: &BTreeSet<&str>) {
+        let Some(Ok(entries)) = self.dir.as_ref().map(std::fs::read_dir) else {
+            return;
+        };
+
+        for entry in entries.filter_map(Result::ok) {
+            let file_name = entry.file_name();
+            if !hashes.contains(file_name.to_string_lossy().as_ref()) {
+                debug!("Removing stored output {}", entry.path().display());
+                if let Err(error) = std::fs::remove_file(entry.path()) {
+                    warn!("Could not remove {}: {error}", entry.path().display());
+                }
+            }
+        }
+    }
+}
+
+/// Keeps the lock of the last run and records the files generated during the
+/// current one.
+#[derive(Debug, Default)]
+pub struct LockRecorder {
+    previous: Lock,
+    current: Mutex<Lock>,
+    files: Mutex<Vec<FileReport>>,
+    conflicts: Mutex<Vec<PathBuf>>,
+}
+
+impl LockRecorder {
+    #[must_use]
+    pub fn new(previous: Lock) -> Self {
+        Self {
+            previous,
+            ..Self::default()
+        }
+    }
+
+    #[must_use]
+    pub fn previous(&self) -> &Lock {
+        &self.previous
+    }
+
+    pub fn record(&self, path: &Path, entry: LockEntry) {
+        self.current
+            .lock()
+            .expect("lock recorder poisoned")
+            .insert(path, entry);
+    }
+
+    /// Records whether the file at `path` has been created, updated or left
+    /// unchanged.
+    pub fn record_file(&self, path: &Path, status: FileStatus) {
+        self.files
+            .lock()
+            .expect("lock recorder poisoned")
+            .push(FileReport::new(path, status));
+    }
+
+    pub fn record_conflict(&self, path: &Path) {
+        self.conflicts
+            .lock()
+            .expect("lock recorder poisoned")
+            .push(path.to_owned());
+    }
+
+    /// Returns the lock of the files generated so far.
+    #[must_use]
+    pub fn current(&self) -> Lock {
+        self.current.lock().expect("lock recorder poisoned").clone()
+    }
+
+    /// Returns the files written so far, in the order they were written.
+    #[must_use]
+    pub fn files(&self) -> Vec<FileReport> {
+        self.files.lock().expect("lock recorder poisoned").clone()
+    }
+
+    /// Returns the files that could not be merged cleanly.
+    #[must_use]
+    pub fn conflicts(&self) -> Vec<PathBuf> {
+        self.conflicts
+            .lock()
+            .expect("lock recorder poisoned")
+            .clone()
+    }
+}
+
+/// The result of merging the changes made by the user into a newly generated
+/// file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeResult {
+    Clean(String),
+    /// The content contains conflict markers.
+    Conflicted(String),
+}
+
+/// Merges the changes made to `existing` since it was generated as recorded in
+/// `previous` into `generated`, using the previously generated content from
+/// `outputs` as the common ancestor.
+///
+/// If the file has not been generated before, or has not been changed since,
+/// `generated` is returned as is. If the previously generated content is not
+/// in `outputs`, e.g. in a fresh clone, the whole files are merged, which
+/// usually results in a conflict rather than in the local changes being lost.
+#[must_use]
+pub fn merge_user_edits(
+    previous: Option<&LockEntry>,
+    outputs: &OutputStore,
+    existing: &str,
+    generated: &str,
+) -> MergeResult {
+    let Some(previous) = previous else {
+        return MergeResult::Clean(generated.to_owned());
+    };
+    if !previous.is_modified(existing) {
+        return MergeResult::Clean(generated.to_owned());
+    }
+
+    let base = outputs.get(previous.output_hash()).unwrap_or_default();
+    let mut merge = TextMerge::from_lines(&base, existing, generated);
+    merge.labels("previously generated", "local", "generated");
+    if merge.is_conflicted() {
+        MergeResult::Conflicted(merge.to_string())
+    } else {
+        MergeResult::Clean(merge.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::path::Path;
+
+    use crate::data::Value;
+    use crate::lock::{
+        hash, merge_user_edits, Lock, LockEntry, MergeResult, OutputStore, LOCK_FILENAME,
+    };
+    use crate::test_utils::TempRepo;
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(
+            hash("test"),
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let repo = TempRepo::new();
+        assert!(Lock::from_repo(&repo.repo()).unwrap().is_empty());
+
+        let mut lock = Lock::new();
+        lock.insert(
+            Path::new(".github/workflows/rust.yml"),
+            LockEntry::new("rust.yml.j2", &Value::empty_object(), "a\nb\n"),
+        );
+        repo.write_str(LOCK_FILENAME, &lock.to_yaml_string());
+
+        let loaded = Lock::from_repo(&repo.repo()).unwrap();
+        assert_eq!(loaded, lock);
+        let entry = loaded.get(Path::new(".github/workflows/rust.yml")).unwrap();
+        assert_eq!(entry.template(), "rust.yml.j2");
+        assert!(!entry.is_modified("a\nb\n"));
+        assert!(entry.is_modified("a\n"));
+    }
+
+    #[test]
+    fn test_output_store() {
+        let dir = TempRepo::new();
+        let outputs = OutputStore::new(dir.path());
+
+        outputs.insert("a\n");
+        outputs.insert("b\n");
+        assert_eq!(outputs.get(&hash("a\n")).as_deref(), Some("a\n"));
+
+        outputs.retain(&BTreeSet::from([hash("b\n").as_str()]));
+        assert_eq!(outputs.get(&hash("a\n")), None);
+        assert_eq!(outputs.get(&hash("b\n")).as_deref(), Some("b\n"));
+        assert_eq!(OutputStore::default().get(&hash("b\n")), None);
+    }
+
+    #[test]
+    fn test_merge_unmodified() {
+        let entry = LockEntry::new("t", &Value::empty_object(), "a\n");
+        let outputs = OutputStore::default();
+
+        assert_eq!(
+            merge_user_edits(Some(&entry), &outputs, "a\n", "b\n"),
+            MergeResult::Clean("b\n".to_owned())
+        );
+        assert_eq!(
+            merge_user_edits(None, &outputs, "local\n", "b\n"),
+            MergeResult::Clean("b\n".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_merge_user_edits() {
+        let entry = LockEntry::new("t", &Value::empty_object(), "a\nb\nc\n");
+        let dir = TempRepo::new();
+        let outputs = OutputStore::new(dir.path());
+        outputs.insert("a\nb\nc\n");
+
+        assert_eq!(
+            merge_user_edits(Some(&entry), &outputs, "a\nb\nc\nlocal\n", "A\nb\nc\n"),
+            MergeResult::Clean("A\nb\nc\nlocal\n".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        let entry = LockEntry::new("t", &Value::empty_object(), "a\nb\n");
+        let dir = TempRepo::new();
+        let outputs = OutputStore::new(dir.path());
+        outputs.insert("a\nb\n");
+
+        let MergeResult::Conflicted(merged) =
+            merge_user_edits(Some(&entry), &outputs, "local\nb\n", "generated\nb\n")
+        else {
+            panic!("expected a conflict");
+        };
+        assert!(merged.contains("<<<<<<< local\nlocal\n=======\ngenerated\n>>>>>>> generated\n"));
+    }
+
+    #[test]
+    fn test_merge_without_output() {
+        let entry = LockEntry::new("t", &Value::empty_object(), "a\n");
+
+        assert!(matches!(
+            merge_user_edits(Some(&entry), &OutputStore::default(), "local\n", "b\n"),
+            MergeResult::Conflicted(_)
+        ));
+    }
+}
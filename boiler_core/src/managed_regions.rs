@@ -0,0 +1,272 @@
+//! Regions of files that are managed by boiler, while the rest of the file is
+//! left to the user.
+//!
+//! A region starts with a `boiler:begin <id>` marker and ends with a
+//! `boiler:end` marker, each on its own line and inside a comment, for
+//! example:
+//!
+//! ```yaml
+//! # boiler:begin jobs
+//! build:
+//!   runs-on: ubuntu-latest
+//! # boiler:end
+//! ```
+//!
+//! Both `#` comments (YAML, TOML, Dockerfiles) and `<!-- -->` comments
+//! (Markdown) are supported.
+
+use std::collections::BTreeSet;
+
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static BEGIN_MARKER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[ \t]*(?:#|<!--)[ \t]*boiler:begin[ \t]+([\w.-]+)[ \t]*(?:-->)?[ \t]*\r?\n?$")
+        .unwrap()
+});
+static END_MARKER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[ \t]*(?:#|<!--)[ \t]*boiler:end[ \t]*(?:-->)?[ \t]*\r?\n?$").unwrap()
+});
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ManagedRegionError {
+    #[error("Region `{id}` starting at line {line} is not terminated")]
+    Unterminated { id: String, line: usize },
+    #[error("Region `{id}` starting at line {line} is nested in another region")]
+    Nested { id: String, line: usize },
+    #[error("Unexpected end of region at line {line}")]
+    UnexpectedEnd { line: usize },
+    #[error("Region `{id}` is defined more than once")]
+    Duplicate { id: String },
+}
+
+/// A managed region; `start..end` is the byte range of its content, excluding
+/// the marker lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    id: String,
+    start: usize,
+    end: usize,
+}
+
+impl Region {
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    #[must_use]
+    pub fn content<'a>(&self, text: &'a str) -> &'a str {
+        &text[self.start..self.end]
+    }
+}
+
+/// Returns the managed regions in `text`, in the order they appear.
+pub fn find_regions(text: &str) -> Result<Vec<Region>, ManagedRegionError> {
+    let mut regions = Vec::new();
+    let mut ids = BTreeSet::new();
+    let mut current: Option<(String, usize, usize)> = None;
+    let mut offset = 0;
+
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        let line_number = index + 1;
+
+        if let Some(captures) = BEGIN_MARKER_REGEX.captures(line) {
+            let id = captures[1].to_owned();
+            if current.is_some() {
+                return Err(ManagedRegionError::Nested {
+                    id,
+                    line: line_number,
+                });
+            }
+            current = Some((id, line_number, offset + line.len()));
+        } else if END_MARKER_REGEX.is_match(line) {
+            let Some((id, _line, start)) = current.take() else {
+                return Err(ManagedRegionError::UnexpectedEnd { line: line_number });
+            };
+            if !ids.insert(id.clone()) {
+                return Err(ManagedRegionError::Duplicate { id });
+            }
+            regions.push(Region {
+                id,
+                start,
+                end: offset,
+            });
+        }
+
+        offset += line.len();
+    }
+
+    if let Some((id, line, _start)) = current {
+        return Err(ManagedRegionError::Unterminated { id, line });
+    }
+
+    Ok(regions)
+}
+
+/// Replaces the content of the managed regions in `existing` with the content
+/// of the regions with the same IDs in `generated`, leaving the rest of
+/// `existing` untouched.
+///
+/// Returns `None` if `existing` does not contain any managed regions, in which
+/// case the whole file is owned by boiler. The same goes for `generated`
+/// without any managed regions, e.g. when it comes from an overridden template
+/// without the markers, so that the file is still updated, and for `generated`
+/// with regions missing from `existing`, which could not be inserted
+/// otherwise.
+pub fn merge_regions(
+    existing: &str,
+    generated: &str,
+) -> Result<Option<String>, ManagedRegionError> {
+    let existing_regions = find_regions(existing)?;
+    if existing_regions.is_empty() {
+        return Ok(None);
+    }
+    let generated_regions = find_regions(generated)?;
+    if generated_regions.is_empty() {
+        return Ok(None);
+    }
+    if let Some(generated_region) = generated_regions.iter().find(|generated_region| {
+        !existing_regions
+            .iter()
+            .any(|region| region.id == generated_region.id)
+    }) {
+        debug!(
+            "Managed region `{}` not found in the existing file; merging the whole file",
+            generated_region.id
+        );
+        return Ok(None);
+    }
+
+    let mut merged = String::with_capacity(existing.len());
+    let mut offset = 0;
+    for region in &existing_regions {
+        let Some(generated_region) = generated_regions
+            .iter()
+            .find(|generated_region| generated_region.id == region.id)
+        else {
+            warn!(
+                "Managed region `{}` is not generated anymore; keeping it as is",
+                region.id
+            );
+            continue;
+        };
+
+        merged.push_str(&existing[offset..region.start]);
+        merged.push_str(generated_region.content(generated));
+        offset = region.end;
+    }
+    merged.push_str(&existing[offset..]);
+
+    Ok(Some(merged))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::managed_regions::{find_regions, merge_regions, ManagedRegionError};
+
+    #[test]
+    fn test_find_regions() {
+        let text = "a\n# boiler:begin one\nb\n# boiler:end\n<!-- boiler:begin two -->\nc\n<!-- boiler:end -->\n";
+
+        let regions = find_regions(text).unwrap();
+
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].id(), "one");
+        assert_eq!(regions[0].content(text), "b\n");
+        assert_eq!(regions[1].id(), "two");
+        assert_eq!(regions[1].content(text), "c\n");
+    }
+
+    #[test]
+    fn test_find_regions_invalid() {
+        assert_eq!(
+            find_regions("# boiler:begin a\n"),
+            Err(ManagedRegionError::Unterminated {
+                id: "a".to_owned(),
+                line: 1
+            })
+        );
+        assert_eq!(
+            find_regions("# boiler:begin a\n  # boiler:begin b\n"),
+            Err(ManagedRegionError::Nested {
+                id: "b".to_owned(),
+                line: 2
+            })
+        );
+        assert_eq!(
+            find_regions("x\n# boiler:end\n"),
+            Err(ManagedRegionError::UnexpectedEnd { line: 2 })
+        );
+        assert_eq!(
+            find_regions("# boiler:begin a\n# boiler:end\n# boiler:begin a\n# boiler:end\n"),
+            Err(ManagedRegionError::Duplicate { id: "a".to_owned() })
+        );
+    }
+
+    #[test]
+    fn test_merge_regions() {
+        let existing = "\
+# boiler:begin jobs
+jobs:
+  build: old
+# boiler:end
+  custom: user
+# boiler:begin missing
+x
+# boiler:end
+";
+        let generated = "# boiler:begin jobs\njobs:\n  build: new\n# boiler:end\n";
+
+        assert_eq!(
+            merge_regions(existing, generated).unwrap().unwrap(),
+            "\
+# boiler:begin jobs
+jobs:
+  build: new
+# boiler:end
+  custom: user
+# boiler:begin missing
+x
+# boiler:end
+"
+        );
+    }
+
+    #[test]
+    fn test_merge_no_regions() {
+        assert_eq!(
+            merge_regions("old\n", "# boiler:begin a\nnew\n# boiler:end\n").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_merge_new_region() {
+        assert_eq!(
+            merge_regions(
+                "# boiler:begin a\nold\n# boiler:end\n",
+                "# boiler:begin b\nnew\n# boiler:end\n# boiler:begin a\nnew\n# boiler:end\n"
+            )
+            .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_merge_generated_no_regions() {
+        assert_eq!(
+            merge_regions(
+                "# boiler:begin a
+old
+# boiler:end
+",
+                "new
+"
+            )
+            .unwrap(),
+            None
+        );
+    }
+}
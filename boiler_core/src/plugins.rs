@@ -0,0 +1,495 @@
+//! Detectors and actions implemented as external executables, which can be
+//! written in any language.
+//!
+//! A plugin is an executable named `boiler-detector-<name>` or
+//! `boiler-action-<name>`. When run, it gets a JSON object with the absolute
+//! `repo` path and the `context` on its standard input, and is run in the
+//! repository root:
+//!
+//! * detectors get the context found by the built-in detectors, and print a
+//!   JSON object that is merged with the results of the other detectors,
+//! * actions get the final context, and print a JSON array of the files to
+//!   write, such as `[{"path": "go.mod", "content": "..."}]`, with the paths
+//!   relative to the repository root.
+//!
+//! When run with the `--description` argument, a plugin prints a short
+//! description of itself, shown in the lists of detectors and actions.
+
+use std::ffi::OsStr;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::actions::{Action, ActionData, ActionResult};
+use crate::context::user_config_dir;
+use crate::data::{is_repo_relative, Repo, Value};
+use crate::detectors::{Detector, DetectorResult};
+use crate::function_meta::FunctionMeta;
+
+/// The prefix of the names of the detector plugin executables.
+pub const DETECTOR_PREFIX: &str = "boiler-detector-";
+/// The prefix of the names of the action plugin executables.
+pub const ACTION_PREFIX: &str = "boiler-action-";
+
+#[derive(Debug, Error)]
+pub enum PluginError {
+    #[error("Could not run plugin {path}: {source}")]
+    Run {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Plugin {path} exited with {status}")]
+    Failed {
+        path: PathBuf,
+        status: std::process::ExitStatus,
+    },
+    #[error("Invalid output of plugin {path}: {source}")]
+    InvalidOutput {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("Plugin {path} tried to write a file outside of the repository: {file}")]
+    InvalidPath { path: PathBuf, file: PathBuf },
+}
+
+/// The plugins found by [`Plugins::discover`].
+#[derive(Debug, Default)]
+pub struct Plugins {
+    pub detectors: Vec<PluginDetector>,
+    pub actions: Vec<PluginAction>,
+}
+
+impl Plugins {
+    /// Finds the plugins in `dirs`; if several plugins have the same name,
+    /// the one in the directory that comes first is used, like with `PATH`.
+    #[must_use]
+    pub fn discover<T: AsRef<Path>>(dirs: &[T]) -> Self {
+        let mut plugins = Self::default();
+
+        for dir in dirs {
+            let Ok(entries) = dir.as_ref().read_dir() else {
+                continue;
+            };
+            // Check the file names first, so that only the plugins are
+            // stat'ed, rather than every file in `PATH`
+            let mut paths: Vec<_> = entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| {
+                    plugin_name(path, DETECTOR_PREFIX).is_some()
+                        || plugin_name(path, ACTION_PREFIX).is_some()
+                })
+                .filter(|path| is_executable(path))
+                .collect();
+            paths.sort();
+
+            for path in paths {
+                if let Some(name) = plugin_name(&path, DETECTOR_PREFIX) {
+                    if !plugins.detectors.iter().any(|plugin| plugin.name() == name) {
+                        debug!("Found detector plugin {name} at {}", path.display());
+                        plugins.detectors.push(PluginDetector::new(name, path));
+                    }
+                } else if let Some(name) = plugin_name(&path, ACTION_PREFIX) {
+                    if !plugins.actions.iter().any(|plugin| plugin.name() == name) {
+                        debug!("Found action plugin {name} at {}", path.display());
+                        plugins.actions.push(PluginAction::new(name, path));
+                    }
+                }
+            }
+        }
+
+        plugins
+    }
+
+    /// Returns the directories searched for plugins by default: `extra_dirs`,
+    /// followed by `~/.config/boiler/plugins` and the directories in `PATH`.
+    #[must_use]
+    pub fn search_dirs(extra_dirs: &[PathBuf]) -> Vec<PathBuf> {
+        let mut dirs = extra_dirs.to_vec();
+        if let Some(config_dir) = user_config_dir() {
+            dirs.push(config_dir.join("plugins"));
+        }
+        if let Some(path) = std::env::var_os("PATH") {
+            dirs.extend(std::env::split_paths(&path));
+        }
+
+        dirs
+    }
+}
+
+/// Returns the name of the plugin at `path`, converted to `PascalCase` like
+/// the names of the built-in detectors and actions (e.g. `GoCi` for
+/// `boiler-action-go-ci`), if its file name starts with `prefix`.
+fn plugin_name(path: &Path, prefix: &str) -> Option<String> {
+    let file_name = if path.extension() == Some(OsStr::new(std::env::consts::EXE_EXTENSION)) {
+        path.file_stem()?
+    } else {
+        path.file_name()?
+    };
+    let suffix = file_name.to_str()?.strip_prefix(prefix)?;
+
+    let name: String = suffix
+        .split(['-', '_'])
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .into_iter()
+                .flat_map(char::to_uppercase)
+                .chain(chars)
+        })
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file() && path.extension() == Some(OsStr::new(std::env::consts::EXE_EXTENSION))
+}
+
+/// The executable of a plugin, along with its lazily loaded description.
+#[derive(Debug)]
+struct PluginExecutable {
+    name: String,
+    path: PathBuf,
+    description: OnceLock<String>,
+}
+
+impl PluginExecutable {
+    fn new(name: String, path: PathBuf) -> Self {
+        Self {
+            name,
+            path,
+            description: OnceLock::new(),
+        }
+    }
+
+    fn description(&self) -> &str {
+        self.description.get_or_init(|| {
+            match self.output(Command::new(&self.path).arg("--description")) {
+                Ok(output) => String::from_utf8_lossy(&output).trim().to_owned(),
+                Err(error) => {
+                    warn!(
+                        "Could not get the description of plugin {}: {error}",
+                        self.name
+                    );
+                    format!("Plugin at {}", self.path.display())
+                }
+            }
+        })
+    }
+
+    /// Runs the plugin with the repository and the context on the standard
+    /// input, and parses its output.
+    fn run<T: for<'de> Deserialize<'de>>(
+        &self,
+        repo: &Repo,
+        context: &Value,
+    ) -> Result<T, PluginError> {
+        let run_error = |source| PluginError::Run {
+            path: self.path.clone(),
+            source,
+        };
+        let repo_path = repo.path().canonicalize().map_err(run_error)?;
+        let input = PluginInput {
+            repo: &repo_path,
+            context,
+        };
+        let input = serde_json::to_vec(&input).expect("could not serialize the plugin input");
+
+        let mut child = Command::new(&self.path)
+            .current_dir(&repo_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(run_error)?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // Write the input in another thread, so that the plugin does not block
+        // on writing its output while boiler is still writing the input
+        let output = std::thread::scope(|scope| {
+            let writer = scope.spawn(move || match stdin.write_all(&input) {
+                // The plugin does not have to read the input
+                Err(error) if error.kind() == ErrorKind::BrokenPipe => Ok(()),
+                result => result,
+            });
+            let output = child.wait_with_output();
+            writer.join().expect("could not write the plugin input")?;
+            output
+        })
+        .map_err(run_error)?;
+        self.check_status(output.status)?;
+
+        serde_json::from_slice(&output.stdout).map_err(|source| PluginError::InvalidOutput {
+            path: self.path.clone(),
+            source,
+        })
+    }
+
+    fn output(&self, command: &mut Command) -> Result<Vec<u8>, PluginError> {
+        let output = command
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|source| PluginError::Run {
+                path: self.path.clone(),
+                source,
+            })?;
+        self.check_status(output.status)?;
+
+        Ok(output.stdout)
+    }
+
+    fn check_status(&self, status: std::process::ExitStatus) -> Result<(), PluginError> {
+        if status.success() {
+            Ok(())
+        } else {
+            Err(PluginError::Failed {
+                path: self.path.clone(),
+                status,
+            })
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PluginInput<'a> {
+    repo: &'a Path,
+    context: &'a Value,
+}
+
+/// A file to write, as returned by an action plugin.
+#[derive(Debug, Deserialize)]
+struct PluginFile {
+    path: PathBuf,
+    content: String,
+}
+
+/// A detector run as an external executable.
+#[derive(Debug)]
+pub struct PluginDetector {
+    executable: PluginExecutable,
+}
+
+impl PluginDetector {
+    #[must_use]
+    pub fn new<T: Into<PathBuf>>(name: String, path: T) -> Self {
+        Self {
+            executable: PluginExecutable::new(name, path.into()),
+        }
+    }
+}
+
+impl FunctionMeta for PluginDetector {
+    fn name(&self) -> &str {
+        &self.executable.name
+    }
+
+    fn description(&self) -> &str {
+        self.executable.description()
+    }
+
+    fn default_enabled(&self) -> bool {
+        true
+    }
+}
+
+impl Detector for PluginDetector {
+    fn detect(&self, repo: &Repo) -> DetectorResult {
+        self.detect_with_context(repo, &Value::empty_object())
+    }
+
+    fn needs_context(&self) -> bool {
+        true
+    }
+
+    fn detect_with_context(&self, repo: &Repo, context: &Value) -> DetectorResult {
+        Ok(self.executable.run(repo, context)?)
+    }
+}
+
+/// An action run as an external executable.
+#[derive(Debug)]
+pub struct PluginAction {
+    executable: PluginExecutable,
+}
+
+impl PluginAction {
+    #[must_use]
+    pub fn new<T: Into<PathBuf>>(name: String, path: T) -> Self {
+        Self {
+            executable: PluginExecutable::new(name, path.into()),
+        }
+    }
+}
+
+impl FunctionMeta for PluginAction {
+    fn name(&self) -> &str {
+        &self.executable.name
+    }
+
+    fn description(&self) -> &str {
+        self.executable.description()
+    }
+
+    fn default_enabled(&self) -> bool {
+        true
+    }
+}
+
+impl Action for PluginAction {
+    fn run(&self, data: &ActionData) -> ActionResult {
+        let files: Vec<PluginFile> = self.executable.run(&data.repo, &data.context)?;
+
+        for file in &files {
+            if !is_repo_relative(&file.path) {
+                return Err(PluginError::InvalidPath {
+                    path: self.executable.path.clone(),
+                    file: file.path.clone(),
+                }
+                .into());
+            }
+        }
+        for file in files {
+            data.write_file(&self.executable.name, file.path, &file.content)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    use crate::actions::{Action, ActionData};
+    use crate::data::Value;
+    use crate::detectors::Detector;
+    use crate::function_meta::FunctionMeta;
+    use crate::plugins::{plugin_name, Plugins, ACTION_PREFIX};
+    use crate::test_utils::TempRepo;
+
+    fn write_plugin(dir: &TempRepo, name: &str, script: &str) {
+        dir.write_str(name, &format!("#!/bin/sh\n{script}"));
+        let path = dir.path().join(name);
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_plugin_name() {
+        assert_eq!(
+            plugin_name(Path::new("/bin/boiler-action-go-ci"), ACTION_PREFIX),
+            Some("GoCi".to_owned())
+        );
+        assert_eq!(
+            plugin_name(Path::new("/bin/boiler-action-"), ACTION_PREFIX),
+            None
+        );
+        assert_eq!(
+            plugin_name(Path::new("/bin/boiler-detector-go"), ACTION_PREFIX),
+            None
+        );
+    }
+
+    #[test]
+    fn test_discover() {
+        let first = TempRepo::new();
+        let second = TempRepo::new();
+        write_plugin(&first, "boiler-detector-go", "echo first\n");
+        write_plugin(&second, "boiler-detector-go", "echo second\n");
+        write_plugin(&second, "boiler-action-go-ci", "echo 'Go CI'\n");
+        first.write_str("boiler-action-not-executable", "");
+
+        let plugins = Plugins::discover(&[first.path(), second.path()]);
+
+        assert_eq!(plugins.detectors.len(), 1);
+        assert_eq!(plugins.detectors[0].name(), "Go");
+        assert_eq!(plugins.detectors[0].description(), "first");
+        assert_eq!(plugins.actions.len(), 1);
+        assert_eq!(plugins.actions[0].name(), "GoCi");
+        assert_eq!(plugins.actions[0].description(), "Go CI");
+    }
+
+    #[test]
+    fn test_detector() {
+        let plugins_dir = TempRepo::new();
+        write_plugin(
+            &plugins_dir,
+            "boiler-detector-go",
+            "grep -q '\"langs\":\\[\"rust\"\\]' && echo '{\"langs\": [\"go\"]}'\n",
+        );
+        let detector = Plugins::discover(&[plugins_dir.path()]).detectors.remove(0);
+        let repo = TempRepo::new();
+
+        let context = Value::new_object([(
+            "langs".to_owned(),
+            Value::new_array([Value::new_string("rust")]),
+        )]);
+        let result = detector
+            .detect_with_context(&repo.repo(), &context)
+            .unwrap();
+
+        assert_eq!(
+            result,
+            Value::new_object([(
+                "langs".to_owned(),
+                Value::new_array([Value::new_string("go")])
+            )])
+        );
+        assert!(detector.needs_context());
+    }
+
+    #[test]
+    fn test_detector_failed() {
+        let plugins_dir = TempRepo::new();
+        write_plugin(&plugins_dir, "boiler-detector-go", "exit 1\n");
+        let detector = Plugins::discover(&[plugins_dir.path()]).detectors.remove(0);
+
+        assert!(detector.detect(&TempRepo::new().repo()).is_err());
+    }
+
+    #[test]
+    fn test_action() {
+        let plugins_dir = TempRepo::new();
+        write_plugin(
+            &plugins_dir,
+            "boiler-action-go-ci",
+            "cat > /dev/null\necho '[{\"path\": \"ci/go.yml\", \"content\": \"go\\\\n\"}]'\n",
+        );
+        let action = Plugins::discover(&[plugins_dir.path()]).actions.remove(0);
+        let repo = TempRepo::new();
+
+        action
+            .run(&ActionData::new(repo.repo(), Value::empty_object()))
+            .unwrap();
+
+        assert_eq!(repo.read_str("ci/go.yml"), "go\n");
+    }
+
+    #[test]
+    fn test_action_outside_repo() {
+        let plugins_dir = TempRepo::new();
+        write_plugin(
+            &plugins_dir,
+            "boiler-action-evil",
+            "echo '[{\"path\": \"../evil\", \"content\": \"\"}]'\n",
+        );
+        let action = Plugins::discover(&[plugins_dir.path()]).actions.remove(0);
+        let repo = TempRepo::new();
+
+        assert!(action
+            .run(&ActionData::new(repo.repo(), Value::empty_object()))
+            .is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use crate::data::Value;
+
+/// The origin of a value in the context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Set by the detector with the given name.
+    Detector(String),
+    /// Set by [`crate::context::default_context_data`].
+    Default,
+    /// Set by a config file; the string describes the file and the entry.
+    Config(String),
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Detector(name) => write!(f, "detector {name}"),
+            Self::Default => write!(f, "default"),
+            Self::Config(config) => write!(f, "config {config}"),
+        }
+    }
+}
+
+/// Keeps track of which [`Source`]s set each value in the context.
+///
+/// The values are identified by their paths, with the object keys separated
+/// with dots (e.g. `langs` or `a.b`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    sources: BTreeMap<String, Vec<Source>>,
+}
+
+impl Provenance {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `value` was merged into the context with
+    /// [`Value::union`], so `source` is added to the existing sources.
+    pub fn record_union(&mut self, value: &Value, source: &Source) {
+        for path in leaf_paths(value) {
+            let sources = self.sources.entry(path).or_default();
+            if !sources.contains(source) {
+                sources.push(source.clone());
+            }
+        }
+    }
+
+    /// Records that `value` was merged into the context with
+    /// [`Value::override_with`], so `source` replaces the existing sources.
+    pub fn record_override(&mut self, value: &Value, source: &Source) {
+        let mut other = Self::new();
+        other.record_union(value, source);
+        self.override_with(&other);
+    }
+
+    /// Records that a context tracked by `other` was merged into the context
+    /// with [`Value::override_with`].
+    pub fn override_with(&mut self, other: &Provenance) {
+        for (path, sources) in &other.sources {
+            self.replace(path, sources.clone());
+        }
+    }
+
+    /// Replaces the sources of the value at `path`, removing the ones of its
+    /// children and parents.
+    pub fn replace(&mut self, path: &str, sources: Vec<Source>) {
+        let child_prefix = format!("{path}.");
+        self.sources.retain(|existing_path, _| {
+            let is_child = existing_path.starts_with(&child_prefix);
+            let is_parent = path.starts_with(&format!("{existing_path}."));
+            !is_child && !is_parent
+        });
+        self.sources.insert(path.to_owned(), sources);
+    }
+
+    /// Returns the sources of the value at `path` and of all its children.
+    pub fn get(&self, path: &str) -> impl Iterator<Item = (&str, &[Source])> {
+        let child_prefix = format!("{path}.");
+        let path = path.to_owned();
+
+        self.sources
+            .iter()
+            .filter(move |(existing_path, _)| {
+                **existing_path == path || existing_path.starts_with(&child_prefix)
+            })
+            .map(|(path, sources)| (path.as_str(), sources.as_slice()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[Source])> {
+        self.sources
+            .iter()
+            .map(|(path, sources)| (path.as_str(), sources.as_slice()))
+    }
+}
+
+fn leaf_paths(value: &Value) -> Vec<String> {
+    let mut paths = Vec::new();
+    if let Some(object) = value.as_object() {
+        for (key, value) in object {
+            collect_leaf_paths(key.clone(), value, &mut paths);
+        }
+    }
+    paths
+}
+
+fn collect_leaf_paths(path: String, value: &Value, paths: &mut Vec<String>) {
+    match value.as_object() {
+        Some(object) if !object.is_empty() => {
+            for (key, value) in object {
+                collect_leaf_paths(format!("{path}.{key}"), value, paths);
+            }
+        }
+        _ => paths.push(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::Value;
+    use crate::provenance::{Provenance, Source};
+
+    #[test]
+    fn test_record_union() {
+        let mut provenance = Provenance::new();
+        let value = Value::new_object([(
+            "langs".to_owned(),
+            Value::new_array([Value::new_string("rust")]),
+        )]);
+
+        provenance.record_union(&value, &Source::Detector("Rust".to_owned()));
+        provenance.record_union(&value, &Source::Detector("Json".to_owned()));
+
+        assert_eq!(
+            provenance.get("langs").collect::<Vec<_>>(),
+            vec![(
+                "langs",
+                [
+                    Source::Detector("Rust".to_owned()),
+                    Source::Detector("Json".to_owned())
+                ]
+                .as_slice()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_record_override() {
+        let mut provenance = Provenance::new();
+        provenance.record_union(
+            &Value::new_object([
+                ("name".to_owned(), Value::new_string("a")),
+                ("license".to_owned(), Value::new_string("MIT")),
+            ]),
+            &Source::Default,
+        );
+
+        provenance.record_override(
+            &Value::new_object([("name".to_owned(), Value::new_string("b"))]),
+            &Source::Config(".boiler.yml".to_owned()),
+        );
+
+        assert_eq!(
+            provenance.iter().collect::<Vec<_>>(),
+            vec![
+                ("license", [Source::Default].as_slice()),
+                (
+                    "name",
+                    [Source::Config(".boiler.yml".to_owned())].as_slice()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_paths() {
+        let mut provenance = Provenance::new();
+        provenance.record_union(
+            &Value::new_object([(
+                "a".to_owned(),
+                Value::new_object([
+                    ("b".to_owned(), Value::new_bool(true)),
+                    ("c".to_owned(), Value::new_bool(false)),
+                ]),
+            )]),
+            &Source::Default,
+        );
+
+        assert_eq!(provenance.get("a").count(), 2);
+        assert_eq!(provenance.get("a.b").count(), 1);
+
+        provenance.record_override(
+            &Value::new_object([("a".to_owned(), Value::new_string("x"))]),
+            &Source::Detector("Test".to_owned()),
+        );
+
+        assert_eq!(
+            provenance.iter().collect::<Vec<_>>(),
+            vec![("a", [Source::Detector("Test".to_owned())].as_slice())]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! The report of what [`run_in_repo`](crate::run_in_repo) did.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+
+/// The result of running the actions in a repository.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RunReport {
+    /// The path to the repository.
+    pub repo: PathBuf,
+    /// All the actions, in the order they were run, including the disabled
+    /// ones.
+    pub actions: Vec<ActionReport>,
+    /// The files where the local changes could not be merged cleanly with the
+    /// newly generated content; these contain conflict markers.
+    pub conflicts: Vec<PathBuf>,
+    /// The files removed because they are no longer generated.
+    pub pruned: Vec<PathBuf>,
+    /// Whether the [`LOCK_FILENAME`](crate::lock::LOCK_FILENAME) has been
+    /// created or updated, which also happens when only the context changed.
+    pub lock_changed: bool,
+    /// The time it took to build the context and run the actions.
+    #[serde(serialize_with = "serialize_secs")]
+    pub duration: Duration,
+}
+
+impl RunReport {
+    /// Returns the files created or updated by any of the actions.
+    #[must_use]
+    pub fn changed(&self) -> Vec<PathBuf> {
+        self.actions
+            .iter()
+            .flat_map(ActionReport::changed)
+            .map(|file| file.path.clone())
+            .collect()
+    }
+
+    /// Returns whether any file was created, updated or removed.
+    #[must_use]
+    pub fn has_changes(&self) -> bool {
+        !self.pruned.is_empty()
+            || self
+                .actions
+                .iter()
+                .any(|action| action.changed().count() > 0)
+    }
+}
+
+/// What a single action did.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ActionReport {
+    pub action: String,
+    pub status: ActionStatus,
+    /// The files generated by the action; empty unless it has been run.
+    pub files: Vec<FileReport>,
+    #[serde(serialize_with = "serialize_secs")]
+    pub duration: Duration,
+}
+
+impl ActionReport {
+    /// Returns the files created or updated by the action.
+    pub fn changed(&self) -> impl Iterator<Item = &FileReport> {
+        self.files.iter().filter(|file| file.status.is_changed())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionStatus {
+    /// The action has been run and generated at least one file.
+    Ran,
+    /// The action has been run, but did not generate any files, e.g. because
+    /// the language it generates files for is not used in the repository.
+    Skipped,
+    /// The action is disabled in the config.
+    Disabled,
+}
+
+/// A file generated by an action.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileReport {
+    /// The path to the file, relative to the repository root.
+    pub path: PathBuf,
+    pub status: FileStatus,
+}
+
+impl FileReport {
+    #[must_use]
+    pub fn new(path: &Path, status: FileStatus) -> Self {
+        Self {
+            path: path.to_owned(),
+            status,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+impl FileStatus {
+    #[must_use]
+    pub fn is_changed(self) -> bool {
+        self != Self::Unchanged
+    }
+}
+
+/// Serializes the duration as a number of seconds.
+fn serialize_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use crate::report::{ActionReport, ActionStatus, FileReport, FileStatus, RunReport};
+
+    #[test]
+    fn test_changed() {
+        let report = RunReport {
+            actions: vec![
+                ActionReport {
+                    action: "Readme".to_owned(),
+                    status: ActionStatus::Ran,
+                    files: vec![
+                        FileReport::new(Path::new("README.md"), FileStatus::Updated),
+                        FileReport::new(Path::new("LICENSE"), FileStatus::Unchanged),
+                    ],
+                    duration: Duration::ZERO,
+                },
+                ActionReport {
+                    action: "RustCi".to_owned(),
+                    status: ActionStatus::Disabled,
+                    files: Vec::new(),
+                    duration: Duration::ZERO,
+                },
+            ],
+            ..RunReport::default()
+        };
+
+        assert_eq!(report.changed(), vec![PathBuf::from("README.md")]);
+        assert!(report.has_changes());
+        assert!(!RunReport::default().has_changes());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let report = RunReport {
+            repo: PathBuf::from("repo"),
+            actions: vec![ActionReport {
+                action: "Readme".to_owned(),
+                status: ActionStatus::Ran,
+                files: vec![FileReport::new(Path::new("README.md"), FileStatus::Created)],
+                duration: Duration::from_millis(1500),
+            }],
+            ..RunReport::default()
+        };
+
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"repo":"repo","actions":[{"action":"Readme","status":"ran","files":[{"path":"README.md","status":"created"}],"duration":1.5}],"conflicts":[],"pruned":[],"lock_changed":false,"duration":0.0}"#
+        );
+    }
+}
@@ -0,0 +1,1041 @@
+//! The engine running the detectors and actions, which can be extended with
+//! custom ones when boiler is used as a library.
+
+use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::Context;
+use log::{debug, info, warn};
+use tera::Tera;
+
+use crate::actions::{self, Action, ActionData, CustomAction, ACTIONS};
+use crate::context::{
+    ContextStage, RepoConfig, RepoConfigError, ReposConfig, REPO_CONFIG_FILENAME,
+};
+use crate::context_keys;
+use crate::context_schema::{self, SchemaError};
+use crate::data::{Repo, Value};
+use crate::detectors::{
+    apply_defaults, create_detectors_enabled_for, merge_detector_results, run_detectors,
+    run_detectors_with_context, Detector, DETECTORS,
+};
+use crate::function_meta::{FunctionEnabled, FunctionMeta};
+use crate::lock::{Lock, OutputStore, LOCK_FILENAME};
+use crate::plugins::Plugins;
+use crate::provenance::{Provenance, Source};
+use crate::report::RunReport;
+use crate::template_renderer::{self, TERA};
+use crate::templates::{TemplateLoader, TemplatesDir};
+use crate::RunOptions;
+
+/// Runs the detectors and actions in repositories.
+///
+/// Use [`Boiler::builder`] to add custom detectors, actions, configs or
+/// templates to the built-in ones.
+pub struct Boiler {
+    detectors: Vec<Arc<dyn Detector>>,
+    detectors_enabled: FunctionEnabled,
+    actions: Vec<Arc<dyn Action>>,
+    repos_config: ReposConfig,
+    config_overrides: Vec<(String, RepoConfig)>,
+    tera: Arc<Tera>,
+}
+
+impl Boiler {
+    /// Returns a builder starting with the built-in detectors, actions and
+    /// config.
+    #[must_use]
+    pub fn builder() -> BoilerBuilder {
+        BoilerBuilder::new()
+    }
+
+    #[must_use]
+    pub fn detectors(&self) -> &[Arc<dyn Detector>] {
+        &self.detectors
+    }
+
+    /// Returns the registered actions, not including the custom actions
+    /// declared in the configs.
+    #[must_use]
+    pub fn actions(&self) -> &[Arc<dyn Action>] {
+        &self.actions
+    }
+
+    /// Returns the registered actions followed by `custom_actions`.
+    pub fn actions_with_custom<'a>(
+        &'a self,
+        custom_actions: &'a [CustomAction],
+    ) -> Result<Vec<&'a dyn Action>, RepoConfigError> {
+        actions::with_custom_actions(&self.actions, custom_actions)
+    }
+
+    #[must_use]
+    pub fn repos_config(&self) -> &ReposConfig {
+        &self.repos_config
+    }
+
+    /// Runs the actions in the repository and updates its [`LOCK_FILENAME`].
+    pub fn run(&self, repo: Repo, options: &RunOptions) -> anyhow::Result<RunReport> {
+        let start = Instant::now();
+        let repo_path = repo.path().to_owned();
+
+        let (data, _provenance, repo_config) = self
+            .build_context_at_stage(&repo, ContextStage::Final)
+            .with_context(|| format!("Could not build context for {}", repo_path.display()))?;
+
+        let custom_actions: Vec<_> = repo_config
+            .custom_actions()
+            .iter()
+            .cloned()
+            .map(CustomAction::new)
+            .collect();
+        let actions = self.actions_with_custom(&custom_actions)?;
+        let actions_enabled =
+            repo_config.create_actions_enabled(&actions::create_actions_enabled_for(&actions))?;
+        let templates_dir = options.templates_dir.clone().or_else(|| {
+            repo_config
+                .templates_dir()
+                .map(|templates_dir| repo_path.join(templates_dir))
+        });
+        let previous_lock = Lock::from_repo(&repo)?;
+        let has_previous_lock = !previous_lock.is_empty();
+        let outputs = OutputStore::for_repo(&repo);
+        let mut action_data = ActionData::new(repo, data)
+            .with_sink(options.sink.clone())
+            .with_tera(self.tera.clone())
+            .with_lock(previous_lock)
+            .with_outputs(outputs)
+            .with_keep_local_changes(options.keep_local_changes);
+        if let Some(templates_dir) = templates_dir {
+            action_data = action_data.with_tera(
+                template_renderer::create_tera(&self.tera, &TemplatesDir::new(templates_dir))
+                    .context("Could not load the templates")?,
+            );
+        }
+        let action_reports = actions::run_actions(&actions, &action_data, &actions_enabled)
+            .with_context(|| format!("Could not run actions for {}", repo_path.display()))?;
+
+        let (lock, pruned) = find_stale_files(&action_data, options.prune)?;
+        let lock_changed = lock != *action_data.lock.previous();
+        if has_previous_lock || !lock.is_empty() {
+            options
+                .sink
+                .write_file(
+                    &action_data.repo,
+                    Path::new(LOCK_FILENAME),
+                    &lock.to_yaml_string(),
+                )
+                .context("Could not write the lock file")?;
+        }
+        // Keep the outputs referenced by the lock on disk as well, in case the
+        // new lock is not written, e.g. in a dry run
+        let hashes = lock
+            .iter()
+            .chain(action_data.lock.previous().iter())
+            .map(|(_path, entry)| entry.output_hash())
+            .collect();
+        action_data.outputs.retain(&hashes);
+
+        Ok(RunReport {
+            repo: repo_path,
+            actions: action_reports,
+            conflicts: action_data.lock.conflicts(),
+            pruned,
+            lock_changed,
+            duration: start.elapsed(),
+        })
+    }
+
+    /// Returns the config that applies to the repository: the matching
+    /// entries of the [`ReposConfig`], its [`REPO_CONFIG_FILENAME`] and the
+    /// config overrides, merged.
+    ///
+    /// As the entries are matched by the repository owner and name, this runs
+    /// the detectors.
+    pub fn repo_config(&self, repo: &Repo) -> anyhow::Result<RepoConfig> {
+        let (_data, _provenance, repo_config) = self
+            .build_context_at_stage(repo, ContextStage::Detected)
+            .with_context(|| format!("Could not build context for {}", repo.path().display()))?;
+
+        Ok(repo_config)
+    }
+
+    /// Builds the context of the repository, as seen at the given stage.
+    pub fn build_context(&self, repo: &Repo, stage: ContextStage) -> anyhow::Result<Value> {
+        Ok(self.build_context_with_provenance(repo, stage)?.0)
+    }
+
+    /// Builds the context like [`Boiler::build_context`], keeping track of
+    /// where each value comes from.
+    pub fn build_context_with_provenance(
+        &self,
+        repo: &Repo,
+        stage: ContextStage,
+    ) -> anyhow::Result<(Value, Provenance)> {
+        let (data, provenance, _repo_config) = self
+            .build_context_at_stage(repo, stage)
+            .with_context(|| format!("Could not build context for {}", repo.path().display()))?;
+
+        Ok((data, provenance))
+    }
+
+    fn build_context_at_stage(
+        &self,
+        repo: &Repo,
+        stage: ContextStage,
+    ) -> anyhow::Result<(Value, Provenance, RepoConfig)> {
+        // The configs matching the repository can only be found with the
+        // detector results, so only the ones that do not depend on them can
+        // select the detectors to run at first
+        let local_configs = self.find_local_repo_configs(repo)?;
+        let detectors_enabled =
+            fold_repo_configs(&local_configs).create_detectors_enabled(&self.detectors_enabled)?;
+        let mut detector_results = run_detectors(repo, &self.detectors, &detectors_enabled)?;
+
+        // The config can change how the detector results are merged, so it has to
+        // be found before merging them
+        let mut repo_configs = self.find_repo_configs(&detector_results);
+        repo_configs.extend(local_configs);
+        let repo_config = fold_repo_configs(&repo_configs);
+        let detectors_enabled = self.select_detectors(
+            repo,
+            &repo_config,
+            &detectors_enabled,
+            &mut detector_results,
+        )?;
+
+        if self
+            .detectors
+            .iter()
+            .any(|detector| detector.needs_context())
+        {
+            let (data, _provenance) =
+                merge_detector_results(&detector_results, repo_config.merge())?;
+            detector_results.extend(run_detectors_with_context(
+                repo,
+                &self.detectors,
+                &detectors_enabled,
+                &data,
+            )?);
+        }
+
+        let (data, provenance) = merge_detector_results(&detector_results, repo_config.merge())?;
+        validate_detected(&data, &provenance)?;
+        if stage == ContextStage::Detected {
+            return Ok((data, provenance, repo_config));
+        }
+
+        let (mut data, mut provenance) = apply_defaults(data, provenance);
+        info!("Detected context:\n{}", data.as_yaml());
+        if stage == ContextStage::Defaults {
+            return Ok((data, provenance, repo_config));
+        }
+
+        for (source, repo_override) in &repo_configs {
+            provenance.record_override(repo_override.context(), source);
+        }
+        info!(
+            "Overriding context with:\n{}",
+            repo_config.context().as_yaml()
+        );
+        data.override_with(repo_config.context());
+
+        info!("New context:\n{}", data.as_yaml());
+
+        Ok((data, provenance, repo_config))
+    }
+
+    /// Returns the entries of the [`ReposConfig`] that apply to the repository,
+    /// from the lowest to the highest priority.
+    fn find_repo_configs(&self, detector_results: &[(&str, Value)]) -> Vec<(Source, RepoConfig)> {
+        let mut repo_configs = Vec::new();
+
+        if let Some(repo_string) = find_repo_string(detector_results) {
+            for (source, repo_override) in self.repos_config.matching(&repo_string) {
+                repo_configs.push((Source::Config(source), repo_override.clone()));
+            }
+        } else {
+            warn!(
+                "Could not detect the repository owner and name; only {REPO_CONFIG_FILENAME} is \
+                 used"
+            );
+        }
+
+        repo_configs
+    }
+
+    /// Returns the [`REPO_CONFIG_FILENAME`] of the repository, followed by the
+    /// config overrides, which take priority over the [`ReposConfig`].
+    fn find_local_repo_configs(&self, repo: &Repo) -> anyhow::Result<Vec<(Source, RepoConfig)>> {
+        let mut repo_configs = Vec::new();
+
+        if let Some(repo_file_config) = RepoConfig::from_repo(repo)? {
+            repo_configs.push((
+                Source::Config(REPO_CONFIG_FILENAME.to_owned()),
+                repo_file_config,
+            ));
+        }
+        for (source, config_override) in &self.config_overrides {
+            repo_configs.push((Source::Config(source.clone()), config_override.clone()));
+        }
+
+        Ok(repo_configs)
+    }
+
+    /// Applies the detector settings of the final `repo_config`: drops the
+    /// results of the detectors it disables, and runs the ones it enables
+    /// that have not been run with `detectors_enabled`.
+    ///
+    /// Returns the detectors enabled by `repo_config`.
+    fn select_detectors<'a>(
+        &'a self,
+        repo: &Repo,
+        repo_config: &RepoConfig,
+        detectors_enabled: &FunctionEnabled,
+        detector_results: &mut Vec<(&'a str, Value)>,
+    ) -> anyhow::Result<FunctionEnabled> {
+        let final_enabled = repo_config.create_detectors_enabled(&self.detectors_enabled)?;
+        detector_results.retain(|(name, _result)| final_enabled.is_enabled(name));
+
+        let mut missing = final_enabled.clone();
+        for (name, is_enabled) in missing.iter_mut() {
+            *is_enabled = *is_enabled && !detectors_enabled.is_enabled(name);
+        }
+        if missing.iter().any(|(_name, is_enabled)| *is_enabled) {
+            detector_results.extend(run_detectors(repo, &self.detectors, &missing)?);
+            // Keep the order of the detectors, which matters when merging
+            detector_results.sort_by_key(|(name, _result)| {
+                self.detectors
+                    .iter()
+                    .position(|detector| detector.name() == *name)
+            });
+        }
+
+        Ok(final_enabled)
+    }
+}
+
+impl Default for Boiler {
+    fn default() -> Self {
+        Self::builder()
+            .build()
+            .expect("could not create the built-in engine")
+    }
+}
+
+impl Debug for Boiler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Boiler")
+            .field("detectors", &function_names(&self.detectors))
+            .field("actions", &function_names(&self.actions))
+            .field("repos_config", &self.repos_config)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Builder for [`Boiler`].
+pub struct BoilerBuilder {
+    detectors: Vec<Arc<dyn Detector>>,
+    actions: Vec<Arc<dyn Action>>,
+    repos_config: ReposConfig,
+    config_overrides: Vec<(String, RepoConfig)>,
+    template_loaders: Vec<Box<dyn TemplateLoader>>,
+}
+
+impl BoilerBuilder {
+    fn new() -> Self {
+        Self {
+            detectors: DETECTORS.to_vec(),
+            actions: ACTIONS.to_vec(),
+            repos_config: ReposConfig::built_in(),
+            config_overrides: Vec::new(),
+            template_loaders: Vec::new(),
+        }
+    }
+
+    /// Registers a detector, which runs after the already registered ones.
+    #[must_use]
+    pub fn detector<T: Detector + 'static>(mut self, detector: T) -> Self {
+        self.detectors.push(Arc::new(detector));
+        self
+    }
+
+    /// Removes the detector with the given name, if it is registered.
+    #[must_use]
+    pub fn remove_detector(mut self, name: &str) -> Self {
+        self.detectors.retain(|detector| detector.name() != name);
+        self
+    }
+
+    /// Registers an action, which runs after the already registered ones.
+    #[must_use]
+    pub fn action<T: Action + 'static>(mut self, action: T) -> Self {
+        self.actions.push(Arc::new(action));
+        self
+    }
+
+    /// Removes the action with the given name, if it is registered.
+    #[must_use]
+    pub fn remove_action(mut self, name: &str) -> Self {
+        self.actions.retain(|action| action.name() != name);
+        self
+    }
+
+    /// Registers the detectors and actions implemented as external
+    /// executables, found e.g. with [`Plugins::discover`].
+    ///
+    /// The detector plugins run after all the other detectors, as they get
+    /// the context found by them. The plugins with the same names as the
+    /// already registered detectors and actions are skipped with a warning,
+    /// so that a stray executable on `PATH` cannot break the build.
+    #[must_use]
+    pub fn plugins(mut self, plugins: Plugins) -> Self {
+        for detector in plugins.detectors {
+            if self
+                .detectors
+                .iter()
+                .any(|other| other.name() == detector.name())
+            {
+                warn!(
+                    "Skipping detector plugin {}: a detector with the same name already exists",
+                    detector.name()
+                );
+            } else {
+                self = self.detector(detector);
+            }
+        }
+        for action in plugins.actions {
+            if self
+                .actions
+                .iter()
+                .any(|other| other.name() == action.name())
+            {
+                warn!(
+                    "Skipping action plugin {}: an action with the same name already exists",
+                    action.name()
+                );
+            } else {
+                self = self.action(action);
+            }
+        }
+        self
+    }
+
+    /// Replaces the config, including the one built into boiler.
+    #[must_use]
+    pub fn repos_config(mut self, repos_config: ReposConfig) -> Self {
+        self.repos_config = repos_config;
+        self
+    }
+
+    /// Adds a config, taking priority over the ones added before.
+    #[must_use]
+    pub fn add_repos_config(mut self, repos_config: ReposConfig) -> Self {
+        self.repos_config.extend(repos_config);
+        self
+    }
+
+    /// Adds a config applied to every repository, taking priority over all
+    /// the other configs, including the [`REPO_CONFIG_FILENAME`] files;
+    /// `source` describes where it comes from, e.g. in `boiler explain`.
+    ///
+    /// As it applies to every repository, it can only select the registered
+    /// actions and detectors, and the custom actions it declares itself.
+    #[must_use]
+    pub fn config_override<T: Into<String>>(mut self, source: T, config: RepoConfig) -> Self {
+        self.config_overrides.push((source.into(), config));
+        self
+    }
+
+    /// Adds a source of templates, taking priority over the built-in templates
+    /// and the ones added before.
+    ///
+    /// The templates from the `templates_dir` set in the config or in
+    /// [`RunOptions`] still take priority over these.
+    #[must_use]
+    pub fn template_loader<T: TemplateLoader + 'static>(mut self, loader: T) -> Self {
+        self.template_loaders.push(Box::new(loader));
+        self
+    }
+
+    /// Loads the templates and creates the engine; fails if several detectors
+    /// or actions have the same name, or if a config override selects an
+    /// unknown one.
+    pub fn build(self) -> anyhow::Result<Boiler> {
+        ensure_unique_names("detector", &self.detectors)?;
+        ensure_unique_names("action", &self.actions)?;
+        for (source, config_override) in &self.config_overrides {
+            validate_config_override(config_override, &self.actions, &self.detectors)
+                .with_context(|| format!("Invalid config override from {source}"))?;
+        }
+
+        let mut tera = TERA.clone();
+        for loader in &self.template_loaders {
+            tera = template_renderer::create_tera(&tera, loader.as_ref())
+                .context("Could not load the templates")?;
+        }
+
+        Ok(Boiler {
+            detectors_enabled: create_detectors_enabled_for(&self.detectors),
+            detectors: self.detectors,
+            actions: self.actions,
+            repos_config: self.repos_config,
+            config_overrides: self.config_overrides,
+            tera,
+        })
+    }
+}
+
+impl Debug for BoilerBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoilerBuilder")
+            .field("detectors", &function_names(&self.detectors))
+            .field("actions", &function_names(&self.actions))
+            .field("repos_config", &self.repos_config)
+            .finish_non_exhaustive()
+    }
+}
+
+fn function_names<T: FunctionMeta + ?Sized>(functions: &[Arc<T>]) -> Vec<&str> {
+    functions.iter().map(|function| function.name()).collect()
+}
+
+fn ensure_unique_names<T: FunctionMeta + ?Sized>(
+    kind: &str,
+    functions: &[Arc<T>],
+) -> anyhow::Result<()> {
+    let names = function_names(functions);
+    for (index, name) in names.iter().enumerate() {
+        if names[..index].contains(name) {
+            anyhow::bail!("Duplicate {kind} name: {name}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the files generated by the previous run, but not by the current one.
+///
+/// If `prune` is set, these files are removed, unless they have been modified
+/// since they were generated. Returns the lock to write, which keeps track of
+/// the files that are not removed, and the list of removed files.
+fn find_stale_files(action_data: &ActionData, prune: bool) -> anyhow::Result<(Lock, Vec<PathBuf>)> {
+    let mut lock = action_data.lock.current();
+    let mut pruned = Vec::new();
+
+    for (path, entry) in action_data.lock.previous().iter() {
+        let path = Path::new(path);
+        if lock.get(path).is_some() {
+            continue;
+        }
+
+        let full_path = action_data.repo.path().join(path);
+        if !full_path.exists() {
+            debug!("Stale file {} does not exist anymore", path.display());
+            continue;
+        }
+        if !prune {
+            lock.insert(path, entry.clone());
+            continue;
+        }
+
+        let content = std::fs::read_to_string(&full_path)
+            .with_context(|| format!("Could not read {}", full_path.display()))?;
+        if entry.is_modified(&content) {
+            warn!(
+                "Not removing {}, as it was modified since it was generated",
+                path.display()
+            );
+            lock.insert(path, entry.clone());
+            continue;
+        }
+
+        info!("Removing {}, as it is no longer generated", path.display());
+        action_data.sink.remove_file(&action_data.repo, path)?;
+        pruned.push(path.to_owned());
+    }
+
+    Ok((lock, pruned))
+}
+
+/// Merges the configs, given from the lowest to the highest priority.
+fn fold_repo_configs(repo_configs: &[(Source, RepoConfig)]) -> RepoConfig {
+    repo_configs.iter().fold(
+        RepoConfig::default(),
+        |mut repo_config, (_source, other)| repo_config.override_with(other),
+    )
+}
+
+/// Checks that the actions and detectors selected in a config override are
+/// registered or declared in the override itself, since the custom actions
+/// of the repositories differ between them.
+fn validate_config_override(
+    config_override: &RepoConfig,
+    actions: &[Arc<dyn Action>],
+    detectors: &[Arc<dyn Detector>],
+) -> Result<(), RepoConfigError> {
+    let custom_actions: Vec<_> = config_override
+        .custom_actions()
+        .iter()
+        .cloned()
+        .map(CustomAction::new)
+        .collect();
+    let actions = actions::with_custom_actions(actions, &custom_actions)?;
+    config_override.create_actions_enabled(&actions::create_actions_enabled_for(&actions))?;
+    config_override.create_detectors_enabled(&create_detectors_enabled_for(detectors))?;
+
+    Ok(())
+}
+
+/// Fails if a detector found a value of a wrong type for a key in
+/// [`CONTEXT_SCHEMA`](crate::context_schema::CONTEXT_SCHEMA); unknown keys are
+/// allowed, as they can be found by custom detectors for custom templates.
+fn validate_detected(data: &Value, provenance: &Provenance) -> anyhow::Result<()> {
+    for error in context_schema::validate(data) {
+        if let SchemaError::TypeMismatch { key, .. } = &error {
+            let sources: Vec<_> = provenance
+                .get(key)
+                .flat_map(|(_path, sources)| sources)
+                .map(ToString::to_string)
+                .collect();
+            anyhow::bail!("{error} (set by {})", sources.join(", "));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the `owner/name` of the repository from the first detector that
+/// found it.
+fn find_repo_string(detector_results: &[(&str, Value)]) -> Option<String> {
+    detector_results.iter().find_map(|(_detector_name, data)| {
+        let data = data.as_object()?;
+        let owner = data.get(context_keys::REPO_OWNER)?.as_string()?;
+        let name = data.get(context_keys::REPO_NAME)?.as_string()?;
+
+        Some(format!("{owner}/{name}"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use boiler_macros::FunctionMeta;
+
+    use crate::actions::{Action, ActionData, ActionName, ActionResult, ACTIONS};
+    use crate::context::{ContextStage, RepoConfig, ReposConfig, REPO_CONFIG_FILENAME};
+    use crate::data::{Repo, Value};
+    use crate::detectors::{Detector, DetectorName, DetectorResult, DETECTORS};
+    use crate::file_sink::MemoryFileSink;
+    use crate::lock::LOCK_FILENAME;
+    use crate::plugins::{PluginAction, PluginDetector, Plugins};
+    use crate::report::ActionStatus;
+    use crate::runner::Boiler;
+    use crate::templates::TemplateLoader;
+    use crate::test_utils::TempRepo;
+    use crate::RunOptions;
+
+    /// Detects the company.
+    #[derive(Debug, FunctionMeta)]
+    struct CompanyDetector;
+
+    impl Detector for CompanyDetector {
+        fn detect(&self, _repo: &Repo) -> DetectorResult {
+            Ok(Value::new_object([(
+                "company".to_owned(),
+                Value::new_string("Acme"),
+            )]))
+        }
+    }
+
+    /// Detects the company website from the company.
+    #[derive(Debug, FunctionMeta)]
+    struct WebsiteDetector;
+
+    impl Detector for WebsiteDetector {
+        fn detect(&self, _repo: &Repo) -> DetectorResult {
+            unreachable!("the detector needs the context")
+        }
+
+        fn needs_context(&self) -> bool {
+            true
+        }
+
+        fn detect_with_context(&self, _repo: &Repo, context: &Value) -> DetectorResult {
+            let company = context
+                .get_path("company")
+                .and_then(Value::as_string)
+                .unwrap_or_default();
+            Ok(Value::new_object([(
+                "website".to_owned(),
+                Value::new_string(format!("{}.example.com", company.to_lowercase())),
+            )]))
+        }
+    }
+
+    /// Detects the repository owner and name.
+    #[derive(Debug, FunctionMeta)]
+    struct RepoNameDetector;
+
+    impl Detector for RepoNameDetector {
+        fn detect(&self, _repo: &Repo) -> DetectorResult {
+            Ok(Value::new_object([
+                ("repo_owner".to_owned(), Value::new_string("m4tx")),
+                ("repo_name".to_owned(), Value::new_string("boiler")),
+            ]))
+        }
+    }
+
+    /// Detects the license as a number.
+    #[derive(Debug, FunctionMeta)]
+    struct NumericLicenseDetector;
+
+    impl Detector for NumericLicenseDetector {
+        fn detect(&self, _repo: &Repo) -> DetectorResult {
+            Ok(Value::new_object([(
+                "license".to_owned(),
+                Value::new_number(1),
+            )]))
+        }
+    }
+
+    /// Generates the company file.
+    #[derive(Debug, FunctionMeta)]
+    struct CompanyAction;
+
+    impl Action for CompanyAction {
+        fn run(&self, data: &ActionData) -> ActionResult {
+            data.render_template("company.txt.j2", Path::new("company.txt"))
+        }
+    }
+
+    /// Generates the company file, unless disabled.
+    #[derive(Debug, FunctionMeta)]
+    #[function_meta(default_enabled = false, name = "OptInCompany")]
+    struct OptInCompanyAction;
+
+    impl Action for OptInCompanyAction {
+        fn run(&self, data: &ActionData) -> ActionResult {
+            CompanyAction.run(data)
+        }
+    }
+
+    struct CompanyTemplates;
+
+    impl TemplateLoader for CompanyTemplates {
+        fn load(&self) -> anyhow::Result<Vec<(String, String)>> {
+            Ok(vec![(
+                "company.txt.j2".to_owned(),
+                "{{ boiler.company }}\n".to_owned(),
+            )])
+        }
+    }
+
+    #[test]
+    fn test_builder() {
+        let repo = TempRepo::new();
+        let mut builder = Boiler::builder()
+            .detector(CompanyDetector)
+            .action(CompanyAction)
+            .template_loader(CompanyTemplates)
+            .remove_detector("Git");
+        for action in ACTIONS.iter() {
+            builder = builder.remove_action(action.name());
+        }
+        let boiler = builder.build().unwrap();
+
+        assert!(boiler
+            .detectors()
+            .iter()
+            .all(|detector| detector.name() != "Git"));
+        let report = boiler.run(repo.repo(), &RunOptions::default()).unwrap();
+
+        assert_eq!(repo.read_str("company.txt"), "Acme\n");
+        assert_eq!(report.actions.len(), 1);
+        assert_eq!(report.actions[0].action, "Company");
+        assert_eq!(report.actions[0].status, ActionStatus::Ran);
+    }
+
+    #[test]
+    fn test_detector_with_context() {
+        let repo = TempRepo::new();
+        let boiler = Boiler::builder()
+            .detector(WebsiteDetector)
+            .detector(CompanyDetector)
+            .build()
+            .unwrap();
+
+        let context = boiler
+            .build_context(&repo.repo(), ContextStage::Detected)
+            .unwrap();
+
+        assert_eq!(context["website"], Value::new_string("acme.example.com"));
+    }
+
+    #[test]
+    fn test_detected_invalid_type() {
+        let repo = TempRepo::new();
+        let boiler = Boiler::builder()
+            .detector(NumericLicenseDetector)
+            .build()
+            .unwrap();
+
+        let error = boiler
+            .build_context(&repo.repo(), ContextStage::Detected)
+            .unwrap_err();
+
+        assert!(format!("{error:#}").contains("detector NumericLicense"));
+    }
+
+    #[test]
+    fn test_select_detectors() {
+        let repo = TempRepo::new();
+        repo.write_str(REPO_CONFIG_FILENAME, "detectors_excluded: [Company]\n");
+        let boiler = Boiler::builder()
+            .detector(CompanyDetector)
+            .detector(WebsiteDetector)
+            .build()
+            .unwrap();
+
+        let context = boiler
+            .build_context(&repo.repo(), ContextStage::Detected)
+            .unwrap();
+        assert!(context.get_path("company").is_none());
+        assert_eq!(context["website"], Value::new_string(".example.com"));
+
+        let boiler = Boiler::builder()
+            .detector(CompanyDetector)
+            .detector(WebsiteDetector)
+            .config_override(
+                "test",
+                RepoConfig::default()
+                    .with_detectors_excluded([DetectorName::new("Website".to_owned())]),
+            )
+            .build()
+            .unwrap();
+        let context = boiler
+            .build_context(&repo.repo(), ContextStage::Detected)
+            .unwrap();
+        assert!(context.get_path("website").is_none());
+    }
+
+    #[test]
+    fn test_repo_config_custom_actions() {
+        let repo = TempRepo::new();
+        repo.write_str(
+            REPO_CONFIG_FILENAME,
+            "custom_actions:\n  - name: Local\n    template: local.j2\n    output: local.txt\n",
+        );
+        let boiler = Boiler::builder()
+            .detector(RepoNameDetector)
+            .repos_config(ReposConfig::from_yaml_string(
+                r#"
+m4tx/*:
+  custom_actions:
+    - name: GoCi
+      template: go.yml.j2
+      output: .github/workflows/go.yml
+other/*:
+  custom_actions:
+    - name: Other
+      template: other.j2
+      output: other.txt
+"#,
+            ))
+            .build()
+            .unwrap();
+
+        let repo_config = boiler.repo_config(&repo.repo()).unwrap();
+        let names: Vec<_> = repo_config
+            .custom_actions()
+            .iter()
+            .map(|action| action.name().name())
+            .collect();
+
+        assert_eq!(names, ["GoCi", "Local"]);
+    }
+
+    #[test]
+    fn test_config_override_actions() {
+        let repo = TempRepo::new();
+        repo.write_str("Cargo.toml", "[package]\nname = \"test\"\n");
+        let boiler = Boiler::builder()
+            .config_override(
+                "test",
+                RepoConfig::default()
+                    .with_actions_included([ActionName::new("RustfmtToml".to_owned())]),
+            )
+            .build()
+            .unwrap();
+
+        let report = boiler.run(repo.repo(), &RunOptions::default()).unwrap();
+
+        for action in &report.actions {
+            let expected = if action.action == "RustfmtToml" {
+                ActionStatus::Ran
+            } else {
+                ActionStatus::Disabled
+            };
+            assert_eq!(action.status, expected, "{}", action.action);
+        }
+    }
+
+    #[test]
+    fn test_config_override_custom_action() {
+        let result = Boiler::builder()
+            .config_override(
+                "test",
+                RepoConfig::default().with_actions_excluded([ActionName::new("GoCi".to_owned())]),
+            )
+            .build();
+        assert!(result.is_err());
+
+        let result = Boiler::builder()
+            .config_override(
+                "test",
+                RepoConfig::default()
+                    .with_detectors_excluded([DetectorName::new("Unknown".to_owned())]),
+            )
+            .build();
+        assert!(result.is_err());
+
+        let config_override = ReposConfig::from_yaml_string(
+            r"
+'*/*':
+  actions_excluded: [GoCi]
+  custom_actions:
+    - name: GoCi
+      template: go.yml.j2
+      output: go.yml
+",
+        )
+        .get("m4tx/boiler")
+        .unwrap();
+        Boiler::builder()
+            .config_override("test", config_override)
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_action_disabled_by_default() {
+        let repo = TempRepo::new();
+        let mut builder = Boiler::builder()
+            .detector(CompanyDetector)
+            .action(OptInCompanyAction)
+            .template_loader(CompanyTemplates);
+        for action in ACTIONS.iter() {
+            builder = builder.remove_action(action.name());
+        }
+        let boiler = builder.build().unwrap();
+
+        let report = boiler.run(repo.repo(), &RunOptions::default()).unwrap();
+        assert_eq!(report.actions[0].status, ActionStatus::Disabled);
+
+        repo.write_str(REPO_CONFIG_FILENAME, "actions_enabled: [OptInCompany]\n");
+        let report = boiler.run(repo.repo(), &RunOptions::default()).unwrap();
+        assert_eq!(report.actions[0].status, ActionStatus::Ran);
+        assert_eq!(repo.read_str("company.txt"), "Acme\n");
+    }
+
+    #[test]
+    fn test_builder_duplicate_name() {
+        let result = Boiler::builder()
+            .detector(CompanyDetector)
+            .detector(CompanyDetector)
+            .build();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Duplicate detector name: Company"
+        );
+    }
+
+    #[test]
+    fn test_plugins_duplicate_name() {
+        let plugins = Plugins {
+            detectors: vec![PluginDetector::new("Git".to_owned(), "boiler-detector-git")],
+            actions: vec![
+                PluginAction::new("RustCi".to_owned(), "boiler-action-rust-ci"),
+                PluginAction::new("GoCi".to_owned(), "boiler-action-go-ci"),
+            ],
+        };
+
+        let boiler = Boiler::builder().plugins(plugins).build().unwrap();
+
+        assert_eq!(boiler.detectors().len(), DETECTORS.len());
+        let action_names: Vec<_> = boiler
+            .actions()
+            .iter()
+            .map(|action| action.name())
+            .collect();
+        assert_eq!(action_names.len(), ACTIONS.len() + 1);
+        assert!(action_names.contains(&"GoCi"));
+    }
+
+    #[test]
+    fn test_repos_config() {
+        let repo = TempRepo::new();
+        let boiler = Boiler::builder()
+            .repos_config(ReposConfig::default())
+            .detector(CompanyDetector)
+            .build()
+            .unwrap();
+
+        let context = boiler
+            .build_context(&repo.repo(), ContextStage::Detected)
+            .unwrap();
+
+        assert_eq!(
+            context.get_path("company"),
+            Some(&Value::new_string("Acme"))
+        );
+    }
+
+    #[test]
+    fn test_local_changes_not_kept() {
+        let repo = TempRepo::new();
+        // The previously generated content is only kept in a git repository
+        gix::init(repo.path()).unwrap();
+        repo.write_str("Cargo.toml", "[package]\nname = \"test\"\n");
+        let mut builder = Boiler::builder();
+        for action in ACTIONS
+            .iter()
+            .filter(|action| action.name() != "RustfmtToml")
+        {
+            builder = builder.remove_action(action.name());
+        }
+        let boiler = builder.build().unwrap();
+        boiler.run(repo.repo(), &RunOptions::default()).unwrap();
+        assert!(repo.path().join(LOCK_FILENAME).exists());
+        let rustfmt_toml = repo.read_str("rustfmt.toml");
+        assert!(!repo.read_str(LOCK_FILENAME).contains(&rustfmt_toml));
+        repo.write_str(
+            "rustfmt.toml",
+            &format!("{rustfmt_toml}edition = \"2015\"\n"),
+        );
+
+        let changed_files = |keep_local_changes| {
+            let sink = Arc::new(MemoryFileSink::new());
+            let options = RunOptions {
+                sink: sink.clone(),
+                keep_local_changes,
+                ..RunOptions::default()
+            };
+            boiler.run(repo.repo(), &options).unwrap();
+            sink.changes(&repo.repo())
+                .unwrap()
+                .into_iter()
+                .map(|change| change.path().to_owned())
+                .filter(|path| path != Path::new(LOCK_FILENAME))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(changed_files(true), Vec::<&Path>::new());
+        assert_eq!(changed_files(false), [Path::new("rustfmt.toml")]);
+    }
+}
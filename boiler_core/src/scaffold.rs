@@ -0,0 +1,221 @@
+//! Generating a commented
+//! [`REPO_CONFIG_FILENAME`](crate::context::REPO_CONFIG_FILENAME) for a
+//! repository.
+
+use std::fmt::Write;
+
+use crate::actions::{Action, CustomAction};
+use crate::context::{default_context_data, ContextStage};
+use crate::context_schema::key_schema;
+use crate::data::{Repo, Value};
+use crate::provenance::Source;
+use crate::Boiler;
+
+/// Returns the content of a
+/// [`REPO_CONFIG_FILENAME`](crate::context::REPO_CONFIG_FILENAME) for the
+/// repository, with all the settings commented out: the values found by the
+/// detectors, the defaults used for the values that were not found, and the
+/// actions that can be excluded.
+pub fn scaffold_repo_config(boiler: &Boiler, repo: &Repo) -> anyhow::Result<String> {
+    let (detected, provenance) =
+        boiler.build_context_with_provenance(repo, ContextStage::Detected)?;
+    let custom_actions: Vec<_> = boiler
+        .repo_config(repo)?
+        .custom_actions()
+        .iter()
+        .cloned()
+        .map(CustomAction::new)
+        .collect();
+    let actions = boiler.actions_with_custom(&custom_actions)?;
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "# The boiler config for this repository, generated by `boiler init`.\n\
+         #\n\
+         # Uncomment and change the settings below to customize the generated files;\n\
+         # the values set here take priority over the ones found by boiler."
+    )?;
+
+    let (enabled, disabled): (Vec<&dyn Action>, Vec<_>) = actions
+        .into_iter()
+        .partition(|action| action.default_enabled());
+    writeln!(out, "\n# Actions that are not run in this repository:")?;
+    writeln!(out, "# actions_excluded:")?;
+    for action in &enabled {
+        write_action(&mut out, *action)?;
+    }
+    if !disabled.is_empty() {
+        writeln!(
+            out,
+            "\n# Actions that are disabled by default, but run in this repository:"
+        )?;
+        writeln!(out, "# actions_enabled:")?;
+        for action in &disabled {
+            write_action(&mut out, *action)?;
+        }
+    }
+
+    writeln!(out, "\n# The values the templates are rendered with:")?;
+    writeln!(out, "# context:")?;
+    let detected = detected.as_object().cloned().unwrap_or_default();
+    if !detected.is_empty() {
+        writeln!(out, "#   # Found in the repository:")?;
+        for (key, value) in &detected {
+            let mut sources: Vec<_> = provenance
+                .get(key)
+                .flat_map(|(_path, sources)| sources)
+                .filter_map(|source| match source {
+                    Source::Detector(name) => Some(name.as_str()),
+                    _ => None,
+                })
+                .collect();
+            sources.dedup();
+            let comment = match key_schema(key) {
+                Some(schema) => format!("{}; found by {}", schema.description, sources.join(", ")),
+                None => format!("Found by {}", sources.join(", ")),
+            };
+            write_value(&mut out, key, value, &comment)?;
+        }
+    }
+
+    let defaults: Vec<_> = default_context_data()
+        .as_object()
+        .cloned()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(key, _value)| !detected.contains_key(key))
+        .collect();
+    if !defaults.is_empty() {
+        writeln!(out, "#\n#   # Not found, so the defaults are used:")?;
+        for (key, value) in &defaults {
+            let description = key_schema(key).map_or("", |schema| schema.description);
+            write_value(&mut out, key, value, description)?;
+        }
+    }
+
+    Ok(out)
+}
+
+/// Writes a commented out list item with the name of `action`.
+fn write_action(out: &mut String, action: &dyn Action) -> std::fmt::Result {
+    write_commented(
+        out,
+        &format!(
+            "  - {}  # {}",
+            action.name(),
+            first_line(action.description())
+        ),
+    )
+}
+
+/// Writes a commented out context entry, with the value in the flow style, so
+/// that it can be uncommented with a single line.
+fn write_value(out: &mut String, key: &str, value: &Value, comment: &str) -> std::fmt::Result {
+    let value = serde_json::to_string(value).expect("could not serialize value to json");
+    write_commented(out, &format!("  {key}: {value}  # {}", first_line(comment)))
+}
+
+/// Writes `text` with every line commented out, so that no setting can be
+/// slipped into the config, e.g. by a multi-line description of a plugin.
+fn write_commented(out: &mut String, text: &str) -> std::fmt::Result {
+    for line in text.lines() {
+        writeln!(out, "# {line}")?;
+    }
+    Ok(())
+}
+
+/// Returns the first line of a description, which can span multiple lines,
+/// e.g. when it comes from a doc comment.
+fn first_line(description: &str) -> &str {
+    description.lines().next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use boiler_macros::FunctionMeta;
+
+    use crate::actions::{Action, ActionData, ActionResult};
+    use crate::context::{RepoConfig, REPO_CONFIG_FILENAME};
+    use crate::data::Value;
+    use crate::scaffold::scaffold_repo_config;
+    use crate::test_utils::TempRepo;
+    use crate::Boiler;
+
+    /// Generates Go CI.
+    /// langs: [evil]
+    #[derive(Debug, FunctionMeta)]
+    struct GoCiAction;
+
+    impl Action for GoCiAction {
+        fn run(&self, _data: &ActionData) -> ActionResult {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_scaffold_repo_config() {
+        let repo = TempRepo::new();
+        repo.write_str("Cargo.toml", "[package]\nname = \"test\"\n");
+
+        let config = scaffold_repo_config(&Boiler::default(), &repo.repo()).unwrap();
+
+        assert!(config.contains("#   - RustCi  # "));
+        assert!(
+            config.contains("#   crate_name: \"test\"  # Name of the Rust crate; found by Rust\n")
+        );
+        assert!(config.contains("#   coverage_enabled: true  # "));
+
+        repo.write_str(REPO_CONFIG_FILENAME, &config);
+        let repo_config = RepoConfig::from_repo(&repo.repo()).unwrap().unwrap();
+        assert_eq!(repo_config.context(), &Value::empty_object());
+    }
+
+    #[test]
+    fn test_scaffold_repo_config_uncommented() {
+        let repo = TempRepo::new();
+        repo.write_str("Cargo.toml", "[package]\nname = \"test\"\n");
+        let config = scaffold_repo_config(&Boiler::default(), &repo.repo()).unwrap();
+
+        let config: String = config
+            .lines()
+            .map(|line| {
+                let uncomment = line == "# actions_excluded:"
+                    || line.starts_with("#   - RustCi ")
+                    || line == "# context:"
+                    || line.starts_with("#   crate_name: ");
+                let line = if uncomment { &line[2..] } else { line };
+                format!("{line}\n")
+            })
+            .collect();
+        repo.write_str(REPO_CONFIG_FILENAME, &config);
+        let repo_config = RepoConfig::from_repo(&repo.repo()).unwrap().unwrap();
+
+        assert_eq!(repo_config.actions_excluded().len(), 1);
+        assert_eq!(
+            repo_config.context(),
+            &Value::new_object([("crate_name".to_owned(), Value::new_string("test"))])
+        );
+    }
+
+    #[test]
+    fn test_scaffold_repo_config_multi_line_description() {
+        let repo = TempRepo::new();
+        repo.write_str(
+            REPO_CONFIG_FILENAME,
+            "custom_actions:\n  - name: Go\n    description: \"Go files\\ncoverage_enabled: false\"\n    template: go.j2\n    output: go.txt\n",
+        );
+        let boiler = Boiler::builder().action(GoCiAction).build().unwrap();
+
+        let config = scaffold_repo_config(&boiler, &repo.repo()).unwrap();
+
+        assert!(config.contains("#   - GoCi  # Generates Go CI.\n"));
+        assert!(config.contains("#   - Go  # Go files\n"));
+        assert!(config
+            .lines()
+            .all(|line| line.is_empty() || line.starts_with('#')));
+        repo.write_str(REPO_CONFIG_FILENAME, &config);
+        let repo_config = RepoConfig::from_repo(&repo.repo()).unwrap().unwrap();
+        assert_eq!(repo_config.context(), &Value::empty_object());
+    }
+}
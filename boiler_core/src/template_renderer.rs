@@ -1,12 +1,16 @@
 use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use once_cell::sync::Lazy;
 use tera::Tera;
 
 use crate::actions::ActionData;
-use crate::actions_utils::{write_file, ActionIoError};
+use crate::actions_utils::write_file;
 use crate::context_keys::CONTEXT_ROOT;
 use crate::data::Value;
+use crate::file_sink::ActionIoError;
+use crate::templates::TemplateLoader;
 
 macro_rules! template {
     ($template_name:literal) => {
@@ -24,6 +28,13 @@ pub enum TemplateRendererError {
 
     #[error("Could not write rendered template")]
     Io(#[from] ActionIoError),
+
+    #[error("Could not read template file: {path}")]
+    TemplateFile {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
 }
 
 pub fn to_yaml_array(
@@ -76,7 +87,7 @@ pub fn path_parent(
     Ok(tera::Value::String(parent))
 }
 
-pub static TERA: Lazy<Tera> = Lazy::new(|| {
+pub static TERA: Lazy<Arc<Tera>> = Lazy::new(|| {
     let mut tera = Tera::default();
     tera.add_raw_templates(vec![
         template!(".pre-commit-config.yaml.j2"),
@@ -92,20 +103,109 @@ pub static TERA: Lazy<Tera> = Lazy::new(|| {
     .expect("could not add raw templates");
     tera.register_filter("to_yaml_array", to_yaml_array);
     tera.register_filter("path_parent", path_parent);
-    tera
+    Arc::new(tera)
 });
 
+/// Creates a template engine with the templates from `base`, overridden by
+/// the ones from `loader` that have the same names.
+///
+/// The templates that do not correspond to any template in `base` are added
+/// as well.
+pub fn create_tera(base: &Tera, loader: &dyn TemplateLoader) -> anyhow::Result<Arc<Tera>> {
+    let templates = loader.load()?;
+
+    let mut tera = base.clone();
+    tera.add_raw_templates(templates)?;
+
+    Ok(Arc::new(tera))
+}
+
 pub fn render_template(file_name: &str, data: &ActionData) -> Result<(), TemplateRendererError> {
     let template_name = format!("{file_name}.j2");
-    let output = TERA
+    let output = data
+        .tera
         .render(&template_name, &build_template_renderer_context(data))
         .map_err(TemplateRendererError::Rendering)?;
 
-    write_file(&data.repo, file_name, &output).map_err(TemplateRendererError::Io)?;
+    write_file(data, &template_name, file_name, &output).map_err(TemplateRendererError::Io)?;
 
     Ok(())
 }
 
+/// Renders `template` to the file at `output`.
+///
+/// `template` is either the name of a template known to the template engine,
+/// or a path to a template file relative to the repository root.
+pub fn render_template_to(
+    template: &str,
+    output: &Path,
+    data: &ActionData,
+) -> Result<(), TemplateRendererError> {
+    render_template_with(template, output, &mut StringRenderer::new(data))
+}
+
+/// Renders `template` to the file at `output` like [`render_template_to`],
+/// using `renderer` for the template files.
+pub fn render_template_with(
+    template: &str,
+    output: &Path,
+    renderer: &mut StringRenderer<'_>,
+) -> Result<(), TemplateRendererError> {
+    let data = renderer.data;
+    let rendered = if data.tera.get_template_names().any(|name| name == template) {
+        data.tera.render(template, &renderer.context)?
+    } else {
+        let path = data.repo.path().join(template);
+        let template_string = std::fs::read_to_string(&path)
+            .map_err(|source| TemplateRendererError::TemplateFile { path, source })?;
+        renderer.render(&template_string)?
+    };
+
+    write_file(data, template, output, &rendered)?;
+
+    Ok(())
+}
+
+/// Evaluates a Tera expression, such as `'rust' in boiler.langs`, in the
+/// context of the templates.
+pub fn evaluate_condition(
+    condition: &str,
+    renderer: &mut StringRenderer<'_>,
+) -> Result<bool, TemplateRendererError> {
+    let output = renderer.render(&format!("{{% if {condition} %}}true{{% endif %}}"))?;
+
+    Ok(output == "true")
+}
+
+/// Renders templates given as strings, such as the template files from the
+/// repository or the conditions of the custom actions, with the filters and
+/// the templates of [`ActionData::tera`].
+///
+/// Rendering a string needs a mutable template engine, so it is cloned when
+/// the first string is rendered, and reused for the following ones.
+pub struct StringRenderer<'a> {
+    data: &'a ActionData,
+    context: tera::Context,
+    tera: Option<Tera>,
+}
+
+impl<'a> StringRenderer<'a> {
+    #[must_use]
+    pub fn new(data: &'a ActionData) -> Self {
+        Self {
+            data,
+            context: build_template_renderer_context(data),
+            tera: None,
+        }
+    }
+
+    fn render(&mut self, template: &str) -> Result<String, tera::Error> {
+        self.tera
+            .get_or_insert_with(|| Tera::clone(&self.data.tera))
+            .render_str(template, &self.context)
+    }
+}
+
 pub fn build_template_renderer_context(data: &ActionData) -> tera::Context {
     Value::new_object(BTreeMap::from([(
         CONTEXT_ROOT.to_string(),
@@ -113,3 +213,37 @@ pub fn build_template_renderer_context(data: &ActionData) -> tera::Context {
     )]))
     .into()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::actions::ActionData;
+    use crate::data::Value;
+    use crate::template_renderer::{create_tera, render_template, TERA};
+    use crate::templates::TemplatesDir;
+    use crate::test_utils::TempRepo;
+
+    #[test]
+    fn test_templates_dir_override() {
+        let templates = TempRepo::new();
+        templates.write_str("rustfmt.toml.j2", "{{ boiler.langs | to_yaml_array }}\n");
+        templates.write_str("custom/file.txt.j2", "custom\n");
+        let repo = TempRepo::new();
+
+        let tera = create_tera(&TERA, &TemplatesDir::new(templates.path())).unwrap();
+        let action_data = ActionData::new(
+            repo.repo(),
+            Value::new_object([(
+                "langs".to_owned(),
+                Value::new_array([Value::new_string("rust")]),
+            )]),
+        )
+        .with_tera(tera);
+        render_template("rustfmt.toml", &action_data).unwrap();
+        render_template("custom/file.txt", &action_data).unwrap();
+        render_template(".pre-commit-config.yaml", &action_data).unwrap();
+
+        assert_eq!(repo.read_str("rustfmt.toml"), "[rust]\n");
+        assert_eq!(repo.read_str("custom/file.txt"), "custom\n");
+        assert!(repo.file_not_empty(".pre-commit-config.yaml"));
+    }
+}
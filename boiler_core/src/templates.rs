@@ -0,0 +1,96 @@
+//! Sources of templates used in addition to the built-in ones.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use path_slash::PathExt;
+
+/// Loads templates in addition to the built-in ones.
+pub trait TemplateLoader: Send + Sync {
+    /// Returns the templates as `(name, content)` pairs. The templates with
+    /// the same names as the built-in ones (such as
+    /// `.github/workflows/rust.yml.j2`) replace them.
+    fn load(&self) -> anyhow::Result<Vec<(String, String)>>;
+}
+
+/// Loads the files in a directory as templates named after their paths
+/// relative to it.
+#[derive(Debug, Clone)]
+pub struct TemplatesDir {
+    path: PathBuf,
+}
+
+impl TemplatesDir {
+    #[must_use]
+    pub fn new<T: Into<PathBuf>>(path: T) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl TemplateLoader for TemplatesDir {
+    fn load(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let mut templates = Vec::new();
+        collect_templates(&self.path, &self.path, &mut templates)?;
+
+        Ok(templates)
+    }
+}
+
+fn collect_templates(
+    templates_dir: &Path,
+    dir: &Path,
+    templates: &mut Vec<(String, String)>,
+) -> anyhow::Result<()> {
+    let context = || format!("Could not read templates directory: {}", dir.display());
+
+    for entry in dir.read_dir().with_context(context)? {
+        let path = entry.with_context(context)?.path();
+        if path.is_dir() {
+            collect_templates(templates_dir, &path, templates)?;
+        } else {
+            let name = path
+                .strip_prefix(templates_dir)
+                .expect("template is not in the templates directory")
+                .to_slash_lossy()
+                .into_owned();
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Could not read template file: {}", path.display()))?;
+            templates.push((name, content));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::templates::{TemplateLoader, TemplatesDir};
+    use crate::test_utils::TempRepo;
+
+    #[test]
+    fn test_templates_dir() {
+        let templates = TempRepo::new();
+        templates.write_str("a.j2", "a");
+        templates.write_str("b/c.j2", "c");
+
+        let mut loaded = TemplatesDir::new(templates.path()).load().unwrap();
+        loaded.sort();
+
+        assert_eq!(
+            loaded,
+            [
+                ("a.j2".to_owned(), "a".to_owned()),
+                ("b/c.j2".to_owned(), "c".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_templates_dir_missing() {
+        let repo = TempRepo::new();
+
+        assert!(TemplatesDir::new(repo.path().join("missing"))
+            .load()
+            .is_err());
+    }
+}
@@ -1,29 +1,176 @@
 use proc_macro2_diagnostics::SpanDiagnosticExt;
 use proc_macro_crate::{crate_name, FoundCrate};
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, DeriveInput, Fields, Item, LitBool, LitStr};
 
-#[proc_macro_derive(FunctionMeta)]
+/// Derives `FunctionMeta` for an action or a detector.
+///
+/// The name is the name of the type without the `Action` or `Detector`
+/// suffix, and the description is its doc comment. Both the name and whether
+/// the function is enabled by default can be set with the `function_meta`
+/// attribute:
+///
+/// ```ignore
+/// /// Generates a Go CI workflow.
+/// #[derive(FunctionMeta)]
+/// #[function_meta(default_enabled = false, name = "GoCi")]
+/// struct ExperimentalGoCiAction;
+/// ```
+#[proc_macro_derive(FunctionMeta, attributes(function_meta))]
 pub fn derive_function_meta(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
+    let options = match FunctionMetaOptions::from_attrs(&input.attrs) {
+        Ok(options) => options,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
     let name = input.ident;
     let name_str = name.to_string();
 
-    if !name_str.ends_with("Action") && !name_str.ends_with("Detector") {
+    let function_name = if let Some(function_name) = options.name {
+        function_name
+    } else if name_str.ends_with("Action") || name_str.ends_with("Detector") {
+        name_str.replace("Action", "").replace("Detector", "")
+    } else {
         return name
             .span()
-            .error("FunctionMeta can only be derived for types ending with 'Action' or 'Detector'")
+            .error(
+                "FunctionMeta can only be derived for types ending with 'Action' or 'Detector', \
+                 unless the name is set with #[function_meta(name = \"...\")]",
+            )
             .emit_as_item_tokens()
             .into();
+    };
+    let default_enabled = options.default_enabled;
+
+    let docs = get_comment(&input.attrs);
+
+    let crate_ident = boiler_core_path();
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let expanded = quote! {
+        impl #impl_generics #crate_ident::function_meta::FunctionMeta for #name #ty_generics #where_clause {
+            fn name(&self) -> &'static str { #function_name }
+            fn description(&self) -> &'static str { #docs }
+            fn default_enabled(&self) -> bool { #default_enabled }
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// Registers an action, so that it is included in `boiler_core::actions::ACTIONS`.
+///
+/// The type has to be a unit struct, or implement `Default`. Actions defined in
+/// other crates are registered as well, as long as the crate is linked in.
+///
+/// ```ignore
+/// /// Generates a Go CI workflow.
+/// #[action]
+/// #[derive(FunctionMeta)]
+/// struct GoCiAction;
+/// ```
+#[proc_macro_attribute]
+pub fn action(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    register(
+        attr,
+        item,
+        "action",
+        "actions",
+        "ActionRegistration",
+        "Action",
+    )
+}
+
+/// Registers a detector, so that it is included in
+/// `boiler_core::detectors::DETECTORS`.
+///
+/// The type has to be a unit struct, or implement `Default`, just like with
+/// [`macro@action`].
+#[proc_macro_attribute]
+pub fn detector(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    register(
+        attr,
+        item,
+        "detector",
+        "detectors",
+        "DetectorRegistration",
+        "Detector",
+    )
+}
+
+/// Emits `item` along with its registration in the `module` of `boiler_core`.
+fn register(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+    kind: &str,
+    module: &str,
+    registration: &str,
+    trait_name: &str,
+) -> proc_macro::TokenStream {
+    if !attr.is_empty() {
+        let attr = proc_macro2::TokenStream::from(attr);
+        return syn::Error::new_spanned(attr, format!("#[{kind}] does not take any arguments"))
+            .to_compile_error()
+            .into();
     }
 
-    let function_name = name_str.replace("Action", "").replace("Detector", "");
+    let item = parse_macro_input!(item as Item);
+    let constructor = match &item {
+        Item::Struct(item_struct) => {
+            let name = &item_struct.ident;
+            if matches!(item_struct.fields, Fields::Unit) {
+                quote! { #name }
+            } else {
+                quote! { <#name as ::core::default::Default>::default() }
+            }
+        }
+        Item::Enum(item_enum) => {
+            let name = &item_enum.ident;
+            quote! { <#name as ::core::default::Default>::default() }
+        }
+        _ => {
+            return syn::Error::new_spanned(
+                &item,
+                format!("#[{kind}] can only be applied to structs and enums"),
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+
+    let crate_ident = boiler_core_path();
+    let module = syn::Ident::new(module, proc_macro2::Span::call_site());
+    let registration = syn::Ident::new(registration, proc_macro2::Span::call_site());
+    let trait_name = syn::Ident::new(trait_name, proc_macro2::Span::call_site());
+    let expanded = quote! {
+        #item
 
-    let docs = get_comment(&input.attrs);
+        const _: () = {
+            fn create() -> ::std::sync::Arc<dyn #crate_ident::#module::#trait_name> {
+                ::std::sync::Arc::new(#constructor)
+            }
+
+            #crate_ident::inventory::submit! {
+                #crate_ident::#module::#registration::new(create)
+            }
+        };
+    };
 
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// Returns the path to `boiler_core`, as seen from the crate being compiled.
+fn boiler_core_path() -> proc_macro2::TokenStream {
     let boiler = crate_name("boiler_core").expect("boiler_core is not present in `Cargo.toml`");
-    let crate_ident = match boiler {
+    match boiler {
         FoundCrate::Itself => {
             quote! { crate }
         }
@@ -31,18 +178,46 @@ pub fn derive_function_meta(input: proc_macro::TokenStream) -> proc_macro::Token
             let ident = syn::Ident::new(&name, proc_macro2::Span::call_site());
             quote! { #ident }
         }
-    };
+    }
+}
 
-    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
-    let expanded = quote! {
-        impl #impl_generics #crate_ident::function_meta::FunctionMeta for #name #ty_generics #where_clause {
-            fn name(&self) -> &'static str { #function_name }
-            fn description(&self) -> &'static str { #docs }
-            fn default_enabled(&self) -> bool { true }
+/// The options set with the `#[function_meta(...)]` attribute.
+struct FunctionMetaOptions {
+    name: Option<String>,
+    default_enabled: bool,
+}
+
+impl FunctionMetaOptions {
+    fn from_attrs(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut options = Self {
+            name: None,
+            default_enabled: true,
+        };
+
+        for attr in attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("function_meta"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    let name: LitStr = meta.value()?.parse()?;
+                    if name.value().is_empty() {
+                        return Err(syn::Error::new(name.span(), "the name cannot be empty"));
+                    }
+                    options.name = Some(name.value());
+                    Ok(())
+                } else if meta.path.is_ident("default_enabled") {
+                    let default_enabled: LitBool = meta.value()?.parse()?;
+                    options.default_enabled = default_enabled.value;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `name` or `default_enabled`"))
+                }
+            })?;
         }
-    };
 
-    proc_macro::TokenStream::from(expanded)
+        Ok(options)
+    }
 }
 
 fn get_comment(attrs: &[syn::Attribute]) -> String {
@@ -4,3 +4,17 @@ fn test_action_meta() {
     t.pass("tests/ui/action_meta.rs");
     t.compile_fail("tests/ui/action_meta_fail.rs");
 }
+
+#[test]
+fn test_function_meta_attrs() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/function_meta_attrs.rs");
+    t.compile_fail("tests/ui/function_meta_attrs_fail.rs");
+}
+
+#[test]
+fn test_register() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/register.rs");
+    t.compile_fail("tests/ui/register_fail.rs");
+}
@@ -0,0 +1,23 @@
+use boiler_core::function_meta::FunctionMeta;
+use boiler_macros::FunctionMeta;
+
+/// Generates GitHub Actions config for Go projects
+#[derive(FunctionMeta)]
+#[function_meta(default_enabled = false)]
+struct GoCiAction;
+
+/// Detects the Go modules
+#[derive(FunctionMeta)]
+#[function_meta(name = "GoModules", default_enabled = true)]
+struct GoMod;
+
+fn main() {
+    let action = GoCiAction;
+    assert_eq!(action.name(), "GoCi");
+    assert_eq!(action.default_enabled(), false);
+
+    let detector = GoMod;
+    assert_eq!(detector.name(), "GoModules");
+    assert_eq!(detector.description(), "Detects the Go modules");
+    assert_eq!(detector.default_enabled(), true);
+}
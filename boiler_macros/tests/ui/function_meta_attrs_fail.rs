@@ -0,0 +1,18 @@
+use boiler_macros::FunctionMeta;
+
+/// Generates GitHub Actions config for Go projects
+#[derive(FunctionMeta)]
+#[function_meta(enabled = false)]
+struct GoCiAction;
+
+/// Generates GitHub Actions config for Go projects
+#[derive(FunctionMeta)]
+#[function_meta(default_enabled = "no")]
+struct GoReleaseAction;
+
+/// Generates GitHub Actions config for Go projects
+#[derive(FunctionMeta)]
+#[function_meta(name = "")]
+struct GoLintAction;
+
+fn main() {}
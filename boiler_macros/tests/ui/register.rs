@@ -0,0 +1,39 @@
+use boiler_core::actions::{Action, ActionData, ActionResult, ACTIONS};
+use boiler_core::data::{Repo, Value};
+use boiler_core::detectors::{Detector, DetectorResult, DETECTORS};
+use boiler_macros::{action, detector, FunctionMeta};
+
+/// Generates GitHub Actions config for Go projects
+#[action]
+#[derive(FunctionMeta)]
+struct GoCiAction;
+
+impl Action for GoCiAction {
+    fn run(&self, _data: &ActionData) -> ActionResult {
+        Ok(())
+    }
+}
+
+/// Detects the Go modules
+#[detector]
+#[derive(Default, FunctionMeta)]
+struct GoModDetector {
+    file_name: String,
+}
+
+impl Detector for GoModDetector {
+    fn detect(&self, _repo: &Repo) -> DetectorResult {
+        assert_eq!(self.file_name, "");
+        Ok(Value::empty_object())
+    }
+}
+
+fn main() {
+    let actions: Vec<_> = ACTIONS.iter().map(|action| action.name()).collect();
+    assert!(actions.contains(&"GoCi"));
+    assert!(actions.contains(&"RustCi"));
+
+    let detectors: Vec<_> = DETECTORS.iter().map(|detector| detector.name()).collect();
+    assert!(detectors.contains(&"GoMod"));
+    assert!(detectors.contains(&"Rust"));
+}
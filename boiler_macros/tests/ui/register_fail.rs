@@ -0,0 +1,9 @@
+use boiler_macros::{action, detector};
+
+#[action]
+fn go_ci() {}
+
+#[detector(priority = 1)]
+struct GoModDetector;
+
+fn main() {}